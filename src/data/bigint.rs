@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+
+// arbitrary-precision whole number used whenever a value outgrows `I64`/`U64`. the
+// magnitude is stored as little-endian digits of base 2^32 without leading zero digits.
+// zero has an empty magnitude and is never negative which keeps the derived `Eq` and
+// `Hash` implementations consistent.
+//
+// division, remainder and shifts follow the semantics of rusts primitive integers:
+// quotients truncate towards zero and bit operations act on the (infinite) two's
+// complement representation.
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BigInt {
+    negative: bool,
+    mag: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn zero() -> Self {
+        Self::default()
+    }
+
+    fn from_parts(negative: bool, mut mag: Vec<u32>) -> Self {
+        trim(&mut mag);
+        Self {
+            negative: negative && !mag.is_empty(),
+            mag,
+        }
+    }
+
+    pub fn one() -> Self {
+        Self::from_parts(false, vec![1])
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mag.is_empty()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.mag.first().is_some_and(|d| d & 1 == 1)
+    }
+
+    // number of bits needed for the magnitude; zero for zero
+    pub fn bits(&self) -> u64 {
+        match self.mag.last() {
+            Some(top) => 32 * self.mag.len() as u64 - u64::from(top.leading_zeros()),
+            _ => 0,
+        }
+    }
+
+    // returns the value if it fits into an `i128`
+    pub fn to_i128(&self) -> Option<i128> {
+        if 4 < self.mag.len() {
+            return None;
+        }
+        let mut mag = 0u128;
+        for (i, d) in self.mag.iter().enumerate() {
+            mag |= (*d as u128) << (32 * i);
+        }
+        if self.negative {
+            if mag <= i128::MAX as u128 + 1 {
+                Some((mag as i128).wrapping_neg())
+            } else {
+                None
+            }
+        } else {
+            i128::try_from(mag).ok()
+        }
+    }
+
+    // the lowest 128 bits in two's complement; used for `as`-like truncating casts
+    pub fn wrapping_i128(&self) -> i128 {
+        let digits = self.to_twos(4.max(self.mag.len() + 1));
+        let mut result = 0u128;
+        for (i, d) in digits.iter().take(4).enumerate() {
+            result |= (*d as u128) << (32 * i);
+        }
+        result as i128
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        let mag = self
+            .mag
+            .iter()
+            .rev()
+            .fold(0f64, |acc, d| acc * 4_294_967_296f64 + *d as f64);
+        if self.negative {
+            -mag
+        } else {
+            mag
+        }
+    }
+
+    // truncates towards zero; `None` for infinite values and NaN
+    pub fn from_f64(from: f64) -> Option<Self> {
+        if !from.is_finite() {
+            return None;
+        }
+        let from = from.trunc();
+        if from.abs() < 2f64.powi(126) {
+            return Some(Self::from(from as i128));
+        }
+        // large floats are whole numbers: mantissa * 2^exponent
+        let bits = from.abs().to_bits();
+        let exponent = ((bits >> 52) & 0x7ff) as usize - 1075;
+        let mantissa = (bits & ((1 << 52) - 1)) | (1 << 52);
+        let mag = Self::from(mantissa as i128).shl(exponent);
+        Some(if from < 0. { mag.neg() } else { mag })
+    }
+
+    pub fn neg(&self) -> Self {
+        Self::from_parts(!self.negative, self.mag.clone())
+    }
+
+    pub fn abs(&self) -> Self {
+        Self::from_parts(false, self.mag.clone())
+    }
+
+    pub fn add(&self, rhs: &Self) -> Self {
+        if self.negative == rhs.negative {
+            return Self::from_parts(self.negative, add_mag(&self.mag, &rhs.mag));
+        }
+        match cmp_mag(&self.mag, &rhs.mag) {
+            Ordering::Less => Self::from_parts(rhs.negative, sub_mag(&rhs.mag, &self.mag)),
+            _ => Self::from_parts(self.negative, sub_mag(&self.mag, &rhs.mag)),
+        }
+    }
+
+    pub fn sub(&self, rhs: &Self) -> Self {
+        self.add(&rhs.neg())
+    }
+
+    pub fn mul(&self, rhs: &Self) -> Self {
+        Self::from_parts(self.negative != rhs.negative, mul_mag(&self.mag, &rhs.mag))
+    }
+
+    // quotient and remainder truncated towards zero; `None` on division by zero
+    pub fn div_rem(&self, rhs: &Self) -> Option<(Self, Self)> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let (quot, rem) = div_rem_mag(&self.mag, &rhs.mag);
+        Some((
+            Self::from_parts(self.negative != rhs.negative, quot),
+            Self::from_parts(self.negative, rem),
+        ))
+    }
+
+    pub fn pow(&self, mut ex: u32) -> Self {
+        let mut base = self.clone();
+        let mut result = Self::one();
+        while 0 < ex {
+            if ex & 1 == 1 {
+                result = result.mul(&base);
+            }
+            ex >>= 1;
+            if 0 < ex {
+                base = base.mul(&base);
+            }
+        }
+        result
+    }
+
+    pub fn shl(&self, n: usize) -> Self {
+        Self::from_parts(self.negative, shl_mag(&self.mag, n))
+    }
+
+    // arithmetic shift; negative numbers are rounded towards negative infinity
+    pub fn shr(&self, n: usize) -> Self {
+        if self.negative {
+            let one = Self::one();
+            let mag = shr_mag(&self.abs().sub(&one).mag, n);
+            Self::from_parts(false, mag).add(&one).neg()
+        } else {
+            Self::from_parts(false, shr_mag(&self.mag, n))
+        }
+    }
+
+    pub fn and(&self, rhs: &Self) -> Self {
+        self.bitwise(rhs, |l, r| l & r)
+    }
+
+    pub fn or(&self, rhs: &Self) -> Self {
+        self.bitwise(rhs, |l, r| l | r)
+    }
+
+    pub fn xor(&self, rhs: &Self) -> Self {
+        self.bitwise(rhs, |l, r| l ^ r)
+    }
+
+    fn bitwise(&self, rhs: &Self, f: impl Fn(u32, u32) -> u32) -> Self {
+        let len = self.mag.len().max(rhs.mag.len()) + 1;
+        let lhs = self.to_twos(len);
+        let rhs = rhs.to_twos(len);
+        Self::from_twos(lhs.iter().zip(rhs.iter()).map(|(l, r)| f(*l, *r)).collect())
+    }
+
+    fn to_twos(&self, len: usize) -> Vec<u32> {
+        let mut digits = self.mag.clone();
+        digits.resize(len, 0);
+        if self.negative {
+            invert_increment(&mut digits);
+        }
+        digits
+    }
+
+    fn from_twos(mut digits: Vec<u32>) -> Self {
+        let negative = digits.last().is_some_and(|d| d & 0x8000_0000 != 0);
+        if negative {
+            invert_increment(&mut digits);
+        }
+        Self::from_parts(negative, digits)
+    }
+}
+
+impl From<i128> for BigInt {
+    fn from(from: i128) -> Self {
+        let mut mag = from.unsigned_abs();
+        let mut digits = vec![];
+        while mag != 0 {
+            digits.push(mag as u32);
+            mag >>= 32;
+        }
+        Self::from_parts(from < 0, digits)
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(from: i64) -> Self {
+        Self::from(from as i128)
+    }
+}
+
+impl From<u64> for BigInt {
+    fn from(from: u64) -> Self {
+        Self::from(from as i128)
+    }
+}
+
+impl std::cmp::Ord for BigInt {
+    fn cmp(&self, rhs: &Self) -> Ordering {
+        match (self.negative, rhs.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_mag(&self.mag, &rhs.mag),
+            (true, true) => cmp_mag(&rhs.mag, &self.mag),
+        }
+    }
+}
+
+impl std::cmp::PartialOrd for BigInt {
+    fn partial_cmp(&self, rhs: &Self) -> Option<Ordering> {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl std::str::FromStr for BigInt {
+    type Err = String;
+    fn from_str(from: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match from.as_bytes().first() {
+            Some(b'-') => (true, &from[1..]),
+            Some(b'+') => (false, &from[1..]),
+            _ => (false, from),
+        };
+        if digits.is_empty() {
+            return Err("not a number".to_string());
+        }
+        let mut mag = vec![];
+        for c in digits.chars() {
+            match c.to_digit(10) {
+                Some(d) => mul_small_add(&mut mag, 10, d),
+                _ => return Err("not a number".to_string()),
+            }
+        }
+        Ok(Self::from_parts(negative, mag))
+    }
+}
+
+impl std::fmt::Display for BigInt {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        // split into chunks of nine decimal digits each
+        let mut chunks = vec![];
+        let mut mag = self.mag.clone();
+        while !mag.is_empty() {
+            let (quot, rem) = div_rem_small(&mag, 1_000_000_000);
+            chunks.push(rem);
+            mag = quot;
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        let mut chunks = chunks.iter().rev();
+        write!(f, "{}", chunks.next().unwrap())?;
+        for chunk in chunks {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+fn trim(mag: &mut Vec<u32>) {
+    while mag.last() == Some(&0) {
+        mag.pop();
+    }
+}
+
+fn cmp_mag(lhs: &[u32], rhs: &[u32]) -> Ordering {
+    lhs.len()
+        .cmp(&rhs.len())
+        .then_with(|| lhs.iter().rev().cmp(rhs.iter().rev()))
+}
+
+fn add_mag(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let (long, short) = if lhs.len() < rhs.len() {
+        (rhs, lhs)
+    } else {
+        (lhs, rhs)
+    };
+    let mut result = Vec::with_capacity(long.len() + 1);
+    let mut carry = 0u64;
+    for (i, d) in long.iter().enumerate() {
+        let sum = *d as u64 + *short.get(i).unwrap_or(&0) as u64 + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    if carry != 0 {
+        result.push(carry as u32);
+    }
+    result
+}
+
+// requires `lhs >= rhs`
+fn sub_mag(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(lhs.len());
+    let mut borrow = 0i64;
+    for (i, d) in lhs.iter().enumerate() {
+        let mut diff = *d as i64 - *rhs.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = if diff < 0 {
+            diff += 1 << 32;
+            1
+        } else {
+            0
+        };
+        result.push(diff as u32);
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_mag(lhs: &[u32], rhs: &[u32]) -> Vec<u32> {
+    if lhs.is_empty() || rhs.is_empty() {
+        return vec![];
+    }
+    let mut result = vec![0u32; lhs.len() + rhs.len()];
+    for (i, l) in lhs.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, r) in rhs.iter().enumerate() {
+            let cur = result[i + j] as u64 + *l as u64 * *r as u64 + carry;
+            result[i + j] = cur as u32;
+            carry = cur >> 32;
+        }
+        result[i + rhs.len()] = carry as u32;
+    }
+    trim(&mut result);
+    result
+}
+
+fn mul_small_add(mag: &mut Vec<u32>, factor: u32, summand: u32) {
+    let mut carry = summand as u64;
+    for d in mag.iter_mut() {
+        let cur = *d as u64 * factor as u64 + carry;
+        *d = cur as u32;
+        carry = cur >> 32;
+    }
+    if carry != 0 {
+        mag.push(carry as u32);
+    }
+}
+
+fn div_rem_small(lhs: &[u32], rhs: u32) -> (Vec<u32>, u32) {
+    let mut quot = vec![0u32; lhs.len()];
+    let mut rem = 0u64;
+    for (i, d) in lhs.iter().enumerate().rev() {
+        let cur = (rem << 32) | *d as u64;
+        quot[i] = (cur / rhs as u64) as u32;
+        rem = cur % rhs as u64;
+    }
+    trim(&mut quot);
+    (quot, rem as u32)
+}
+
+// binary long division; `rhs` must not be zero
+fn div_rem_mag(lhs: &[u32], rhs: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    if cmp_mag(lhs, rhs) == Ordering::Less {
+        return (vec![], lhs.to_vec());
+    }
+    if rhs.len() == 1 {
+        let (quot, rem) = div_rem_small(lhs, rhs[0]);
+        return (quot, if rem == 0 { vec![] } else { vec![rem] });
+    }
+    let mut quot = vec![0u32; lhs.len()];
+    let mut rem: Vec<u32> = vec![];
+    for i in (0..lhs.len() * 32).rev() {
+        rem = shl_mag(&rem, 1);
+        if lhs[i / 32] >> (i % 32) & 1 == 1 {
+            match rem.first_mut() {
+                Some(d) => *d |= 1,
+                _ => rem.push(1),
+            }
+        }
+        if cmp_mag(&rem, rhs) != Ordering::Less {
+            rem = sub_mag(&rem, rhs);
+            quot[i / 32] |= 1 << (i % 32);
+        }
+    }
+    trim(&mut quot);
+    (quot, rem)
+}
+
+fn shl_mag(mag: &[u32], n: usize) -> Vec<u32> {
+    if mag.is_empty() {
+        return vec![];
+    }
+    let (digits, bits) = (n / 32, n % 32);
+    let mut result = vec![0u32; digits];
+    let mut carry = 0u32;
+    for d in mag.iter() {
+        if bits == 0 {
+            result.push(*d);
+        } else {
+            result.push((d << bits) | carry);
+            carry = d >> (32 - bits);
+        }
+    }
+    if carry != 0 {
+        result.push(carry);
+    }
+    result
+}
+
+fn shr_mag(mag: &[u32], n: usize) -> Vec<u32> {
+    let (digits, bits) = (n / 32, n % 32);
+    if mag.len() <= digits {
+        return vec![];
+    }
+    let mag = &mag[digits..];
+    let mut result = Vec::with_capacity(mag.len());
+    for (i, d) in mag.iter().enumerate() {
+        if bits == 0 {
+            result.push(*d);
+        } else {
+            let high = mag.get(i + 1).map_or(0, |h| h << (32 - bits));
+            result.push((d >> bits) | high);
+        }
+    }
+    trim(&mut result);
+    result
+}
+
+// negation inside two's complement
+fn invert_increment(digits: &mut [u32]) {
+    let mut carry = true;
+    for d in digits.iter_mut() {
+        let (sum, overflow) = (!*d).overflowing_add(carry as u32);
+        *d = sum;
+        carry = overflow;
+    }
+}
@@ -24,9 +24,26 @@ pub type CodeBlock = Vec<Code>;
 // used as entry point of execution.
 pub type Program = Unit;
 
+// describes how whole number arithmetic behaves if the result does not fit into the
+// operands type. division and remainder by zero are always an error.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub enum ArithmeticMode {
+    // discard the overflowing bits
+    Wrapping,
+    // stop execution with a vm error
+    #[default]
+    Checked,
+    // clamp the result to the types bounds
+    Saturating,
+    // redo the operation inside the next wider type
+    Promote,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct CodeObject {
     pub argc: usize,
+    // overrides the vm-wide `ArithmeticMode` if set
+    pub arithmetic: Option<ArithmeticMode>,
     pub space: Space,
     pub inner: CodeBlock,
 }
@@ -35,6 +52,7 @@ impl CodeObject {
     pub fn new() -> Self {
         Self {
             argc: 0,
+            arithmetic: None,
             space: Space::new(),
             inner: CodeBlock::new(),
         }
@@ -62,7 +80,8 @@ impl Space {
     }
 }
 
-// the bytecode definition of lovm
+// the bytecode definition of lovm. the variant order determines the serialized opcode, so
+// new instructions must be appended
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
 #[repr(u8)]
 pub enum Protocol<T> {
@@ -101,6 +120,7 @@ pub enum Protocol<T> {
     GPop(T),  // pop to global
     GCall(T),
 
+    // convert the value on top of the stack into `ValueType` with the given index
     Cast(T),
     Int(T),
     Ret,
@@ -115,11 +135,44 @@ pub enum Protocol<T> {
     ONewDict,
     // dispose the last object on stack
     ODispose,
-    // use constant at this index for accessing/calling object attributes
+    // use constant at this index for accessing/calling object attributes. both
+    // keep the object on the stack; `OGet` pushes the value on top of it.
     OGet(T),
     OSet(T),
     OCall(T),
     OAppend,
+    // replace the value on top of the stack with the name of its `ValueType`
+    TypeOf,
+    // replace the value on top of the stack with `true` if it is of the given `ValueType`
+    IsType(T),
+    // raise an error if the value on top of the stack is not of the given `ValueType`
+    AssertType(T),
+    // replace the object on top of the stack with an iterator over it
+    OIter,
+    // advance the iterator on top of the stack pushing the next item. if it is
+    // exhausted, the iterator is popped and execution continues at the given offset
+    ONext(T),
+    // like `OGet` and `OSet` but the key is popped from the stack. for `OSetDyn`
+    // the key is located below the value.
+    OGetDyn,
+    OSetDyn,
+    // exchange the two values on top of the stack
+    Swap,
+    // discard the value on top of the stack
+    Pop,
+    // like `OCall` but the lookup starts at the parent of the type defining the
+    // running method
+    OSuper(T),
+    // replace the value on top of the stack with `true` if it is an object of the
+    // type named by the global at this index or one of its descendants
+    OInstanceOf(T),
+    // create a new set pushing its handle onto the stack
+    ONewSet,
+    // pop the given amount of values and push the handle of a tuple containing
+    // them. equal tuples share the same handle.
+    ONewTuple(T),
+    // make the object on top of the stack read-only; it stays on the stack
+    OFreeze,
 }
 
 impl Code {
@@ -127,6 +180,8 @@ impl Code {
         match self {
             Code::Int(c)
             | Code::Cast(c)
+            | Code::IsType(c)
+            | Code::AssertType(c)
             | Code::Jmp(c)
             | Code::Jt(c)
             | Code::Jf(c)
@@ -138,9 +193,13 @@ impl Code {
             | Code::GPop(c)
             | Code::GCall(c)
             | Code::ONew(c)
+            | Code::ONewTuple(c)
             | Code::OGet(c)
             | Code::OSet(c)
-            | Code::OCall(c) => Some(*c),
+            | Code::OCall(c)
+            | Code::OSuper(c)
+            | Code::OInstanceOf(c)
+            | Code::ONext(c) => Some(*c),
             _ => None,
         }
     }
@@ -149,6 +208,8 @@ impl Code {
         match self {
             Code::Int(c)
             | Code::Cast(c)
+            | Code::IsType(c)
+            | Code::AssertType(c)
             | Code::Jmp(c)
             | Code::Jt(c)
             | Code::Jf(c)
@@ -160,9 +221,13 @@ impl Code {
             | Code::GPop(c)
             | Code::GCall(c)
             | Code::ONew(c)
+            | Code::ONewTuple(c)
             | Code::OGet(c)
             | Code::OSet(c)
-            | Code::OCall(c) => Some(c),
+            | Code::OCall(c)
+            | Code::OSuper(c)
+            | Code::OInstanceOf(c)
+            | Code::ONext(c) => Some(c),
             _ => None,
         }
     }
@@ -171,6 +236,8 @@ impl Code {
         match self {
             Code::Int(_)
             | Code::Cast(_)
+            | Code::IsType(_)
+            | Code::AssertType(_)
             | Code::Jmp(_)
             | Code::Jt(_)
             | Code::Jf(_)
@@ -182,9 +249,13 @@ impl Code {
             | Code::GPop(_)
             | Code::GCall(_)
             | Code::ONew(_)
+            | Code::ONewTuple(_)
             | Code::OGet(_)
             | Code::OSet(_)
-            | Code::OCall(_) => 1,
+            | Code::OCall(_)
+            | Code::OSuper(_)
+            | Code::OInstanceOf(_)
+            | Code::ONext(_) => 1,
             _ => 0,
         }
     }
@@ -200,6 +271,8 @@ impl std::fmt::Display for Code {
 pub struct Unit {
     pub space: Space,
     pub inner: Vec<(Name, CodeObjectRef)>,
+    // if the unit is loaded as a type, it inherits all methods of this type
+    pub parent: Option<Name>,
 }
 
 impl Unit {
@@ -207,6 +280,7 @@ impl Unit {
         Self {
             space: Space::new(),
             inner: vec![],
+            parent: None,
         }
     }
 
@@ -1,6 +1,5 @@
 use super::*;
 
-use serde::de::*;
 use serde::*;
 
 // if we return a `CodeObject` in lookup calls, we give the promise that it stays
@@ -42,19 +41,10 @@ impl Serialize for CodeObjectRef {
     }
 }
 
-struct CodeObjectRefVisitor;
-
-impl<'de> Visitor<'de> for CodeObjectRefVisitor {
-    type Value = CodeObject;
-
-    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "a code object ref")
-    }
-}
-
 impl<'de> Deserialize<'de> for CodeObjectRef {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let co = deserializer.deserialize_any(CodeObjectRefVisitor)?;
+        // bincode is not self-describing, so the code object must be requested explicitly
+        let co = CodeObject::deserialize(deserializer)?;
         Ok(CodeObjectRef::from(co))
     }
 }
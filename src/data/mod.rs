@@ -1,10 +1,12 @@
 use super::*;
 
+pub mod bigint;
 pub mod code;
 pub mod coref;
 pub mod uref;
 pub mod value;
 
+pub use bigint::*;
 pub use code::*;
 pub use coref::*;
 pub use uref::*;
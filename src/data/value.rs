@@ -1,28 +1,143 @@
 use self::Value::*;
 
+use super::*;
+
 use serde::{Deserialize, Serialize};
 
+use std::hash::Hash;
+
 pub type ObjectId = usize;
 
 // TODO: replace this with `Cow<...>` to reduce memory usage and improve performance (?)
 pub type Str = String;
 
+// the variant order determines the serialized discriminant, so new variants must be appended
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum Value {
     I(i8),
     I64(i64),
     F64(f64),
-    Ref(usize),
+    // handle of an object inside the `ObjectPool`; not a number
+    Ref(ObjectId),
     T(bool),
     C(char),
     Str(Str),
+    I16(i16),
+    I32(i32),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    // absence of a value e.g. uninitialized locals or functions without return value
+    Nil,
+    // arbitrary-precision whole number
+    Big(BigInt),
+}
+
+// runtime type of a `Value`. the discriminant is used as argument for the `Cast`, `IsType`
+// and `AssertType` instructions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[repr(usize)]
+pub enum ValueType {
+    Nil = 0,
+    I = 1,
+    I64 = 2,
+    F64 = 3,
+    Ref = 4,
+    T = 5,
+    C = 6,
+    Str = 7,
+    I16 = 8,
+    I32 = 9,
+    U8 = 10,
+    U16 = 11,
+    U32 = 12,
+    U64 = 13,
+    F32 = 14,
+    Big = 15,
+}
+
+impl ValueType {
+    pub const ALL: [ValueType; 16] = [
+        ValueType::Nil,
+        ValueType::I,
+        ValueType::I64,
+        ValueType::F64,
+        ValueType::Ref,
+        ValueType::T,
+        ValueType::C,
+        ValueType::Str,
+        ValueType::I16,
+        ValueType::I32,
+        ValueType::U8,
+        ValueType::U16,
+        ValueType::U32,
+        ValueType::U64,
+        ValueType::F32,
+        ValueType::Big,
+    ];
+
+    pub fn from_index(idx: usize) -> Option<Self> {
+        Self::ALL.get(idx).cloned()
+    }
+
+    // name used by the `TypeOf` instruction
+    pub fn name(&self) -> &'static str {
+        match self {
+            ValueType::Nil => "nil",
+            ValueType::I => "i8",
+            ValueType::I16 => "i16",
+            ValueType::I32 => "i32",
+            ValueType::I64 => "i64",
+            ValueType::U8 => "u8",
+            ValueType::U16 => "u16",
+            ValueType::U32 => "u32",
+            ValueType::U64 => "u64",
+            ValueType::Big => "big",
+            ValueType::F32 => "f32",
+            ValueType::F64 => "f64",
+            ValueType::Ref => "ref",
+            ValueType::T => "bool",
+            ValueType::C => "char",
+            ValueType::Str => "str",
+        }
+    }
+}
+
+impl std::str::FromStr for ValueType {
+    type Err = String;
+    fn from_str(from: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|ty| ty.name() == from)
+            .cloned()
+            .ok_or_else(|| format!("unknown type `{}`", from))
+    }
+}
+
+impl std::fmt::Display for ValueType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "{}", self.name())
+    }
 }
 
 impl std::hash::Hash for Value {
     fn hash<H: std::hash::Hasher>(&self, hash: &mut H) {
+        // must stay consistent with the strict equality defined in `PartialEq`
+        std::mem::discriminant(self).hash(hash);
         match self {
+            Nil => hash.write_u8(0),
             I(n) => hash.write_i8(*n),
+            I16(n) => hash.write_i16(*n),
+            I32(n) => hash.write_i32(*n),
             I64(n) => hash.write_i64(*n),
+            U8(n) => hash.write_u8(*n),
+            U16(n) => hash.write_u16(*n),
+            U32(n) => hash.write_u32(*n),
+            U64(n) => hash.write_u64(*n),
+            Big(n) => n.hash(hash),
+            F32(n) => hash.write_u32(n.to_bits()),
             F64(n) => hash.write_u64(n.to_bits()),
             Ref(n) => hash.write_usize(*n),
             T(t) => hash.write_u8(if *t { 0 } else { 1 }),
@@ -32,23 +147,35 @@ impl std::hash::Hash for Value {
     }
 }
 
-impl std::convert::From<Value> for usize {
-    fn from(v: Value) -> usize {
-        match v {
-            I(n) => n as usize,
-            I64(n) => n as usize,
-            F64(n) => n as usize,
-            Ref(n) => n,
-            T(t) => {
-                if t {
-                    1
-                } else {
-                    0
-                }
+// used for counts like the argument count of `OCall`. negative and fractional
+// parts are not accepted.
+impl std::convert::TryFrom<Value> for usize {
+    type Error = String;
+    fn try_from(v: Value) -> Result<usize, String> {
+        let float = |n: f64| {
+            if 0. <= n && n.fract() == 0. && n <= usize::MAX as f64 {
+                Some(n as usize)
+            } else {
+                None
             }
-            C(c) => c as usize,
-            Str(_) => unimplemented!(),
-        }
+        };
+        let n = match &v {
+            I(n) => usize::try_from(*n).ok(),
+            I16(n) => usize::try_from(*n).ok(),
+            I32(n) => usize::try_from(*n).ok(),
+            I64(n) => usize::try_from(*n).ok(),
+            U8(n) => Some(usize::from(*n)),
+            U16(n) => Some(usize::from(*n)),
+            U32(n) => usize::try_from(*n).ok(),
+            U64(n) => usize::try_from(*n).ok(),
+            Big(n) => n.to_i128().and_then(|n| usize::try_from(n).ok()),
+            F32(n) => float(f64::from(*n)),
+            F64(n) => float(*n),
+            T(t) => Some(usize::from(*t)),
+            C(c) => Some(*c as usize),
+            Nil | Ref(_) | Str(_) => None,
+        };
+        n.ok_or_else(|| format!("cannot convert `{}` into usize", v))
     }
 }
 
@@ -58,9 +185,15 @@ impl std::convert::From<i8> for Value {
     }
 }
 
+impl std::convert::From<i16> for Value {
+    fn from(n: i16) -> Value {
+        Value::I16(n)
+    }
+}
+
 impl std::convert::From<i32> for Value {
     fn from(n: i32) -> Value {
-        Value::I64(n as i64)
+        Value::I32(n)
     }
 }
 
@@ -70,15 +203,52 @@ impl std::convert::From<i64> for Value {
     }
 }
 
+impl std::convert::From<u8> for Value {
+    fn from(n: u8) -> Value {
+        Value::U8(n)
+    }
+}
+
+impl std::convert::From<u16> for Value {
+    fn from(n: u16) -> Value {
+        Value::U16(n)
+    }
+}
+
+impl std::convert::From<u32> for Value {
+    fn from(n: u32) -> Value {
+        Value::U32(n)
+    }
+}
+
+impl std::convert::From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::U64(n)
+    }
+}
+
+impl std::convert::From<BigInt> for Value {
+    fn from(n: BigInt) -> Value {
+        Value::Big(n)
+    }
+}
+
+impl std::convert::From<f32> for Value {
+    fn from(n: f32) -> Value {
+        Value::F32(n)
+    }
+}
+
 impl std::convert::From<f64> for Value {
     fn from(n: f64) -> Value {
         Value::F64(n)
     }
 }
 
+// lengths and indices are unsigned numbers; handles must be created via `Value::Ref`
 impl std::convert::From<usize> for Value {
     fn from(n: usize) -> Value {
-        Value::Ref(n)
+        Value::U64(n as u64)
     }
 }
 
@@ -98,6 +268,7 @@ impl std::convert::From<Value> for bool {
     fn from(s: Value) -> bool {
         match s {
             Value::T(t) => t,
+            Value::Nil => false,
             _ => panic!("cannot convert `{:?}` into bool", s),
         }
     }
@@ -105,34 +276,82 @@ impl std::convert::From<Value> for bool {
 
 impl std::cmp::Eq for Value {}
 
+// numeric literals may carry a type suffix (e.g. `10u16`, `1.5f32`). literals without
+// suffix are parsed into the smallest fitting of `I` and `I64` or `F64` for floats.
+const TYPE_SUFFIXES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "u8", "u16", "u32", "u64", "f32", "f64",
+];
+
 impl std::str::FromStr for Value {
     type Err = String;
     fn from_str(from: &str) -> Result<Self, Self::Err> {
         match from {
+            "nil" => Ok(Value::Nil),
             "true" => Ok(Value::T(true)),
             "false" => Ok(Value::T(false)),
-            _ if from.contains(".") => match f64::from_str(from) {
-                Ok(val) => Ok(Value::F64(val)),
-                _ => Err("not a float".to_string()),
-            },
             _ => {
+                if let Some(suffix) = TYPE_SUFFIXES.iter().find(|suffix| from.ends_with(*suffix)) {
+                    return Value::from_str_typed(&from[..from.len() - suffix.len()], suffix);
+                }
+                if from.contains('.') {
+                    return match f64::from_str(from) {
+                        Ok(val) => Ok(Value::F64(val)),
+                        _ => Err("not a float".to_string()),
+                    };
+                }
                 const MIN: i64 = i8::min_value() as i64;
                 const MAX: i64 = i8::max_value() as i64;
                 match i64::from_str(from) {
                     Ok(val @ MIN..=MAX) => Ok(Value::I(val as i8)),
                     Ok(val) => Ok(Value::I64(val)),
-                    _ => Err("not a number".to_string()),
+                    // literals exceeding `I64` are stored with arbitrary precision
+                    _ => BigInt::from_str(from).map(Value::Big),
                 }
             }
         }
     }
 }
 
+impl Value {
+    fn from_str_typed(from: &str, suffix: &str) -> Result<Self, String> {
+        use std::str::FromStr;
+        macro_rules! parse {
+            ($ty:ty, $variant:ident) => {
+                <$ty>::from_str(from)
+                    .map(Value::$variant)
+                    .map_err(|_| format!("not a valid `{}`", suffix))
+            };
+        }
+        match suffix {
+            "i8" => parse!(i8, I),
+            "i16" => parse!(i16, I16),
+            "i32" => parse!(i32, I32),
+            "i64" => parse!(i64, I64),
+            "u8" => parse!(u8, U8),
+            "u16" => parse!(u16, U16),
+            "u32" => parse!(u32, U32),
+            "u64" => parse!(u64, U64),
+            "f32" => parse!(f32, F32),
+            "f64" => parse!(f64, F64),
+            _ => Err(format!("unknown type suffix `{}`", suffix)),
+        }
+    }
+}
+
 impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         match self {
+            Value::Nil => write!(f, "nil"),
             Value::I(arg) => write!(f, "{}", arg),
+            Value::I16(arg) => write!(f, "{}", arg),
+            Value::I32(arg) => write!(f, "{}", arg),
             Value::I64(arg) => write!(f, "{}", arg),
+            Value::U8(arg) => write!(f, "{}", arg),
+            Value::U16(arg) => write!(f, "{}", arg),
+            Value::U32(arg) => write!(f, "{}", arg),
+            Value::U64(arg) => write!(f, "{}", arg),
+            Value::Big(arg) => write!(f, "{}", arg),
+            Value::F32(arg) => write!(f, "{}", arg),
             Value::F64(arg) => write!(f, "{}", arg),
             Value::Ref(arg) => write!(f, "{}", arg),
             Value::T(arg) => write!(f, "{}", arg),
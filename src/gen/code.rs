@@ -1,5 +1,7 @@
 use super::*;
 
+use std::convert::TryFrom;
+
 impl CodeObject {
     pub fn merge(&mut self, other: &Self) {
         // at which location will the branch be added?
@@ -17,7 +19,11 @@ impl CodeObject {
         for inx in other.inner.iter_mut() {
             if let Some(prev_idx) = inx.arg() {
                 let new_idx = match inx {
-                    Code::CPush(_) => {
+                    Code::CPush(_)
+                    | Code::OGet(_)
+                    | Code::OSet(_)
+                    | Code::OCall(_)
+                    | Code::OSuper(_) => {
                         let prev_val = &other.space.consts[prev_idx];
                         index_of(&mut self.space.consts, prev_val)
                     }
@@ -42,7 +48,18 @@ impl CodeObject {
                             index_of(&mut self.space.globals, prev_val)
                         }
                     }
-                    Code::Jmp(bidx) | Code::Jt(bidx) | Code::Jf(bidx) => {
+                    // types are always looked up globally
+                    Code::ONew(_) | Code::OInstanceOf(_) => {
+                        let prev_val = &other.space.globals[prev_idx];
+                        index_of(&mut self.space.globals, prev_val)
+                    }
+                    // arguments that do not refer to the `Space`
+                    Code::Int(arg)
+                    | Code::Cast(arg)
+                    | Code::IsType(arg)
+                    | Code::AssertType(arg)
+                    | Code::ONewTuple(arg) => *arg,
+                    Code::Jmp(bidx) | Code::Jt(bidx) | Code::Jf(bidx) | Code::ONext(bidx) => {
                         // if this panics, no branch resolve was done
                         assert!(*bidx < std::usize::MAX);
 
@@ -63,6 +80,7 @@ impl CodeObject {
 #[derive(Clone, Debug, PartialEq)]
 pub struct CodeBuilder {
     argc: usize,
+    arithmetic: Option<ArithmeticMode>,
     branches: Vec<CodeBuilder>,
     space: Space,
     seq: Sequence,
@@ -72,6 +90,7 @@ impl CodeBuilder {
     pub fn new() -> Self {
         Self {
             argc: 0,
+            arithmetic: None,
             branches: vec![],
             space: Space::new(),
             seq: Sequence::new(),
@@ -102,6 +121,12 @@ impl CodeBuilder {
         self
     }
 
+    // overflow behavior for whole numbers inside this function
+    pub fn with_arithmetic(&mut self, mode: ArithmeticMode) -> &mut Self {
+        self.arithmetic = Some(mode);
+        self
+    }
+
     pub fn debug(&mut self) -> &mut Self {
         self.seq.push(Operation::new(OperationType::Debug));
         self
@@ -146,6 +171,15 @@ impl CodeBuilder {
         self.jump(target.into(), OperationType::Jf)
     }
 
+    // jumps to `target` if the local or global `name` holds a value of type `ty`
+    pub fn branch_if_type<T>(&mut self, name: &str, ty: ValueType, target: T) -> &mut Self
+    where
+        T: Into<BranchTarget>,
+    {
+        self.step(gen::is_type(ty).var(name).end());
+        self.branch_if(target)
+    }
+
     pub fn step(&mut self, op: Operation) -> &mut Self {
         for c in op.consts() {
             if !self.space.consts.contains(c) {
@@ -153,11 +187,18 @@ impl CodeBuilder {
             }
         }
 
+        self.declare(&op);
+        self.seq.push(op);
+        self
+    }
+
+    // register assignment targets as locals
+    fn declare(&mut self, op: &Operation) {
         match op.ty {
-            OperationType::Ass => {
+            OperationType::Ass | OperationType::ForEach => {
                 if let Some(target) = op.target() {
                     let name = target.as_name();
-                    if !self.space.locals.contains(&name) {
+                    if !self.space.locals.contains(name) {
                         self.space.locals.push(name.clone());
                     }
                 }
@@ -165,8 +206,28 @@ impl CodeBuilder {
             _ => {}
         }
 
-        self.seq.push(op);
-        self
+        // loop bodies are translated inline
+        if op.ty == OperationType::ForEach {
+            for stmt in op.rest() {
+                if let OpValue::Operation(stmt) = stmt {
+                    self.declare(stmt);
+                }
+            }
+        }
+    }
+
+    // runs `body` for every item of `iterable` which is stored in the local `name`.
+    // `iterable` must evaluate to an object supporting the iteration protocol.
+    pub fn for_each<T>(&mut self, name: &str, iterable: T, body: Sequence) -> &mut Self
+    where
+        T: Into<OpValue>,
+    {
+        let mut op = Operation::new(OperationType::ForEach);
+        op.var(name).op(iterable);
+        for stmt in body.into_iter() {
+            op.op(stmt);
+        }
+        self.step(op)
     }
 
     // TODO: the parameter obsfucates build calls; maybe remove it again
@@ -176,6 +237,7 @@ impl CodeBuilder {
 
         let mut func = CodeObject::new();
         func.argc = self.argc.clone();
+        func.arithmetic = self.arithmetic;
         func.space = self.space.clone();
 
         translate_sequence(&mut func, self.seq.clone(), &mut offsets)?;
@@ -378,17 +440,43 @@ fn translate_operation(
                     _ => unreachable!(),
                 };
                 if let Some(OpValue::Operand(jmp_offset)) = op.ops().next() {
-                    offsets.push((func.inner.len(), jmp_offset.as_const().clone().into()));
+                    let offset = usize::try_from(jmp_offset.as_const().clone()).map_err(|_| ())?;
+                    offsets.push((func.inner.len(), offset));
                 }
                 func.inner.push(inx);
             }
             OperationType::Int => match op.ops().next() {
                 Some(OpValue::Operand(idx)) => {
-                    let idx = idx.as_const().clone().into();
+                    let idx = usize::try_from(idx.as_const().clone()).map_err(|_| ())?;
                     func.inner.extend(vec![Code::Int(idx)])
                 }
                 _ => panic!("interrupt not specified"),
             },
+            OperationType::Cast
+            | OperationType::TypeOf
+            | OperationType::IsType
+            | OperationType::AssertType => {
+                // first argument is the target type, other arguments are pushed before
+                let mut ops = op.ops();
+                let ty = match op.ty {
+                    OperationType::TypeOf => None,
+                    _ => match ops.next() {
+                        Some(OpValue::Operand(Operand::Const(Value::Str(name)))) => {
+                            Some(name.parse::<ValueType>().map_err(|_| ())? as usize)
+                        }
+                        _ => panic!("type not specified"),
+                    },
+                };
+                for arg in ops {
+                    translate(func, arg, Access::Read, offsets)?;
+                }
+                func.inner.push(match (&op.ty, ty) {
+                    (OperationType::Cast, Some(ty)) => Code::Cast(ty),
+                    (OperationType::IsType, Some(ty)) => Code::IsType(ty),
+                    (OperationType::AssertType, Some(ty)) => Code::AssertType(ty),
+                    _ => Code::TypeOf,
+                });
+            }
             OperationType::Debug => {
                 func.inner
                     .extend(vec![Code::Int(vm::Interrupt::Debug as usize)]);
@@ -415,6 +503,20 @@ fn translate_operation(
                     func.inner.push(Code::OAppend);
                 }
             }
+            // like arrays, every argument is added to the set
+            OperationType::ONewSet => {
+                func.inner.extend(vec![Code::ONewSet]);
+                for arg in op.ops() {
+                    translate(func, arg, Access::Read, offsets)?;
+                    func.inner.push(Code::OAppend);
+                }
+            }
+            OperationType::ONewTuple => {
+                for arg in op.ops() {
+                    translate(func, arg, Access::Read, offsets)?;
+                }
+                func.inner.push(Code::ONewTuple(op.ops().count()));
+            }
             OperationType::ONewDict => {
                 func.inner.extend(vec![Code::ONewDict]);
                 for arg in op.ops() {
@@ -427,7 +529,25 @@ fn translate_operation(
                     func.inner.push(Code::OAppend);
                 }
             }
-            OperationType::OGet => unimplemented!(),
+            // every key is applied on the result of the previous one e.g.
+            // `oget().op("x").var("i")` is equal to `obj.x[i]`. the object is
+            // dropped after each step, so only the last value stays on the stack.
+            OperationType::OGet => {
+                for key in op.ops() {
+                    match key {
+                        OpValue::Operand(Operand::Const(key)) => {
+                            let idx = index_of(&mut func.space.consts, key);
+                            func.inner.push(Code::OGet(idx));
+                        }
+                        key => {
+                            translate(func, key, Access::Read, offsets)?;
+                            func.inner.push(Code::OGetDyn);
+                        }
+                    }
+                    func.inner.push(Code::Swap);
+                    func.inner.push(Code::Pop);
+                }
+            }
             OperationType::OSet => {
                 let mut it = op.ops();
                 loop {
@@ -437,12 +557,18 @@ fn translate_operation(
                             let idx = index_of(&mut func.space.consts, &key);
                             func.inner.push(Code::OSet(idx));
                         }
-                        (Some(key), _) => panic!("incorrect key `{:?}`", key),
+                        // computed keys are pushed before the value
+                        (Some(key), Some(val)) => {
+                            translate(func, key, Access::Read, offsets)?;
+                            translate(func, val, Access::Read, offsets)?;
+                            func.inner.push(Code::OSetDyn);
+                        }
+                        (Some(key), _) => panic!("no value for key `{:?}`", key),
                         _ => break,
                     }
                 }
             }
-            OperationType::OCall => {
+            OperationType::OCall | OperationType::OSuper => {
                 let mut argc = 0;
 
                 // push arguments onto stack
@@ -456,7 +582,38 @@ fn translate_operation(
 
                 let fname = op.target().unwrap().as_name();
                 let idx = index_of(&mut func.space.consts, &Value::from(fname.as_ref()));
-                func.inner.push(Code::OCall(idx));
+                func.inner.push(match op.ty {
+                    OperationType::OSuper => Code::OSuper(idx),
+                    _ => Code::OCall(idx),
+                });
+            }
+            OperationType::InstanceOf => {
+                let ty_name = op.target().unwrap().as_name();
+                for arg in op.rest() {
+                    translate(func, arg, Access::Read, offsets)?;
+                }
+                let idx = index_of(&mut func.space.globals, &ty_name);
+                func.inner.push(Code::OInstanceOf(idx));
+            }
+            OperationType::ForEach => {
+                // first operand is the loop variable, second the iterable. all
+                // other operands form the loop body.
+                let mut ops = op.rest();
+                let iterable = ops.next().expect("no iterable");
+                translate(func, iterable, Access::Read, offsets)?;
+                func.inner.push(Code::OIter);
+
+                let start = func.inner.len();
+                func.inner.push(Code::ONext(usize::MAX));
+                translate_operand(func, op.target().unwrap(), Access::Write)?;
+                for stmt in ops {
+                    translate(func, stmt, Access::Read, offsets)?;
+                }
+                func.inner.push(Code::Jmp(start));
+
+                // leave the loop once the iterator is exhausted
+                let end = func.inner.len();
+                func.inner[start] = Code::ONext(end);
             }
             other => panic!("`{:?}` not yet implemented", other),
         }
@@ -23,6 +23,10 @@ pub enum OperationType {
 
     Call,
     Int,
+    Cast,
+    TypeOf,
+    IsType,
+    AssertType,
     Ret,
     Push,
     Pop,
@@ -30,11 +34,18 @@ pub enum OperationType {
     ONew,
     ONewArray,
     ONewDict,
+    ONewSet,
+    ONewTuple,
+    OFreeze,
     ODispose,
     OAppend,
     OGet,
     OSet,
     OCall,
+    OSuper,
+    InstanceOf,
+    // loop over an iterable; see `CodeBuilder::for_each`
+    ForEach,
 
     CmpEq,
     CmpNe, // actually short for `CmpEq; Not`
@@ -68,6 +79,16 @@ pub fn ocall(fname: &str) -> Operation {
     Operation::new(OperationType::OCall).var(fname).end()
 }
 
+// call a method of the parent type; only valid inside methods
+pub fn osuper(fname: &str) -> Operation {
+    Operation::new(OperationType::OSuper).var(fname).end()
+}
+
+// the type is stored by name; remaining operands are pushed before the check
+pub fn instance_of(ty_name: &str) -> Operation {
+    Operation::new(OperationType::InstanceOf).op(ty_name).end()
+}
+
 pub fn int(idx: usize) -> Operation {
     Operation::new(OperationType::Int).op(idx).end()
 }
@@ -76,6 +97,21 @@ pub fn onew(ty_name: &str) -> Operation {
     Operation::new(OperationType::ONew).op(ty_name).end()
 }
 
+// converts the value of the following operand into `ty`
+pub fn cast(ty: ValueType) -> Operation {
+    Operation::new(OperationType::Cast).op(ty.name()).end()
+}
+
+pub fn is_type(ty: ValueType) -> Operation {
+    Operation::new(OperationType::IsType).op(ty.name()).end()
+}
+
+pub fn assert_type(ty: ValueType) -> Operation {
+    Operation::new(OperationType::AssertType)
+        .op(ty.name())
+        .end()
+}
+
 impl Operation {
     pub fn call(fname: &str) -> Self {
         call(fname)
@@ -85,6 +121,14 @@ impl Operation {
         ocall(fname)
     }
 
+    pub fn osuper(fname: &str) -> Self {
+        osuper(fname)
+    }
+
+    pub fn instance_of(ty_name: &str) -> Self {
+        instance_of(ty_name)
+    }
+
     pub fn int(idx: usize) -> Self {
         int(idx)
     }
@@ -92,15 +136,31 @@ impl Operation {
     pub fn onew(ty_name: &str) -> Self {
         onew(ty_name)
     }
+
+    pub fn cast(ty: ValueType) -> Self {
+        cast(ty)
+    }
+
+    pub fn is_type(ty: ValueType) -> Self {
+        is_type(ty)
+    }
+
+    pub fn assert_type(ty: ValueType) -> Self {
+        assert_type(ty)
+    }
 }
 
 derive_constructor!(OperationType::Ass, ass);
 derive_constructor!(OperationType::Debug, debug);
 derive_constructor!(OperationType::Ret, ret);
+derive_constructor!(OperationType::TypeOf, type_of);
 derive_constructor!(OperationType::Push, push);
 derive_constructor!(OperationType::Pop, pop);
 derive_constructor!(OperationType::ONewArray, onewarray);
 derive_constructor!(OperationType::ONewDict, onewdict);
+derive_constructor!(OperationType::ONewSet, onewset);
+derive_constructor!(OperationType::ONewTuple, onewtuple);
+derive_constructor!(OperationType::OFreeze, ofreeze);
 derive_constructor!(OperationType::ODispose, odispose);
 derive_constructor!(OperationType::OAppend, oappend);
 derive_constructor!(OperationType::OGet, oget);
@@ -159,7 +219,7 @@ impl From<CodeBuilder> for OpValue {
     }
 }
 
-// constructor for arrays (tuples)
+// constructor for arrays. use `onewtuple` for immutable sequences.
 impl<T> From<Vec<T>> for OpValue
 where
     T: Into<OpValue>,
@@ -174,7 +234,7 @@ where
 }
 
 // TODO: this is ugly
-// constructor for objects (sets)
+// constructor for dicts
 impl<T> From<Vec<(Option<T>, T)>> for OpValue
 where
     T: Into<OpValue>,
@@ -232,6 +292,7 @@ impl Operation {
             OperationType::Shr => Some(Code::Shr),
 
             OperationType::ODispose => Some(Code::ODispose),
+            OperationType::OFreeze => Some(Code::OFreeze),
             _ => None,
         }
     }
@@ -3,16 +3,21 @@ use super::*;
 #[derive(Clone, Debug)]
 pub struct UnitBuilder {
     slots: Vec<(Name, CodeObject)>,
+    parent: Option<Name>,
 }
 
 impl UnitBuilder {
     pub fn new() -> Self {
-        Self { slots: vec![] }
+        Self {
+            slots: vec![],
+            parent: None,
+        }
     }
 
     pub fn from_object(co: CodeObject) -> Self {
         Self {
             slots: vec![("main".to_string(), co)],
+            parent: None,
         }
     }
 
@@ -36,8 +41,18 @@ impl UnitBuilder {
         self
     }
 
+    // the unit inherits all methods of type `parent` when loaded as a type
+    pub fn derive<T>(&mut self, parent: T) -> &mut Self
+    where
+        T: std::string::ToString,
+    {
+        self.parent = Some(parent.to_string());
+        self
+    }
+
     pub fn build(&self) -> BuildResult<Unit> {
         let mut unit = Unit::new();
+        unit.parent = self.parent.clone();
         for (name, co) in self.slots.iter() {
             unit.inner
                 .push((name.clone(), CodeObjectRef::from(co.clone())));
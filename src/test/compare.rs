@@ -0,0 +1,167 @@
+#![cfg(test)]
+use super::*;
+
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+fn samples() -> Vec<Value> {
+    vec![
+        Value::Nil,
+        Value::T(false),
+        Value::T(true),
+        Value::I(-1),
+        Value::I(0),
+        Value::I(1),
+        Value::I16(1),
+        Value::I32(1),
+        Value::I64(1),
+        Value::I64(-1),
+        Value::U8(1),
+        Value::U16(1),
+        Value::U32(1),
+        Value::U64(1),
+        Value::U64(u64::max_value()),
+        Value::Big(BigInt::from(1i64)),
+        Value::Big(BigInt::from(u64::max_value() as i128 + 1)),
+        Value::F32(1.),
+        Value::F64(1.),
+        Value::F64(1.5),
+        Value::F64(0.),
+        Value::F64(-0.),
+        Value::F64(std::f64::NAN),
+        Value::F64(std::f64::INFINITY),
+        Value::F64(std::f64::NEG_INFINITY),
+        Value::Ref(1),
+        Value::C('a'),
+        Value::C('b'),
+        Value::from("a"),
+        Value::from("b"),
+    ]
+}
+
+fn hash_of(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn strict_equality() {
+    let samples = samples();
+    for (i, lhs) in samples.iter().enumerate() {
+        for (j, rhs) in samples.iter().enumerate() {
+            // every sample is distinct under strict equality
+            assert_eq!(lhs == rhs, i == j, "{:?} == {:?}", lhs, rhs);
+            if lhs == rhs {
+                assert_eq!(hash_of(lhs), hash_of(rhs));
+            }
+        }
+    }
+    assert_eq!(Value::C('a'), Value::C('a'));
+    assert_eq!(Value::F64(std::f64::NAN), Value::F64(std::f64::NAN));
+}
+
+#[test]
+fn total_order() {
+    let samples = samples();
+    for lhs in samples.iter() {
+        for rhs in samples.iter() {
+            let ord = lhs.cmp(rhs);
+            assert_eq!(
+                ord == Ordering::Equal,
+                lhs == rhs,
+                "{:?} cmp {:?}",
+                lhs,
+                rhs
+            );
+            assert_eq!(ord.reverse(), rhs.cmp(lhs), "{:?} cmp {:?}", lhs, rhs);
+            assert_eq!(lhs.partial_cmp(rhs), Some(ord));
+            for other in samples.iter() {
+                if lhs <= rhs && rhs <= other {
+                    assert!(lhs <= other, "{:?} <= {:?} <= {:?}", lhs, rhs, other);
+                }
+            }
+        }
+    }
+
+    let mut sorted = vec![
+        Value::from("a"),
+        Value::F64(0.5),
+        Value::C('z'),
+        Value::I(1),
+        Value::Nil,
+        Value::I64(-3),
+        Value::T(true),
+    ];
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        vec![
+            Value::Nil,
+            Value::T(true),
+            Value::I64(-3),
+            Value::F64(0.5),
+            Value::I(1),
+            Value::C('z'),
+            Value::from("a"),
+        ]
+    );
+}
+
+#[test]
+fn loose_equality() {
+    let samples = samples();
+    for lhs in samples.iter() {
+        for rhs in samples.iter() {
+            let ord = lhs.loose_cmp(rhs);
+            assert_eq!(ord.map(Ordering::reverse), rhs.loose_cmp(lhs));
+            assert_eq!(lhs.loose_eq(rhs), ord == Some(Ordering::Equal));
+            // strict equality implies loose equality except for NaN
+            let is_nan = *lhs == Value::F64(std::f64::NAN);
+            if lhs == rhs && !is_nan {
+                assert!(lhs.loose_eq(rhs), "{:?} ~= {:?}", lhs, rhs);
+            }
+            // loose ordering agrees with the total order where it is defined
+            if let Some(ord) = ord {
+                if ord != Ordering::Equal {
+                    assert_eq!(lhs.cmp(rhs), ord, "{:?} cmp {:?}", lhs, rhs);
+                }
+            }
+        }
+    }
+
+    let ones = samples.iter().filter(|v| v.loose_eq(&Value::I(1))).count();
+    assert_eq!(ones, 11);
+    assert!(Value::F64(0.).loose_eq(&Value::F64(-0.)));
+    assert!(!Value::F64(std::f64::NAN).loose_eq(&Value::F64(std::f64::NAN)));
+    assert!(!Value::T(true).loose_eq(&Value::I(1)));
+    assert!(Value::from("a").loose_cmp(&Value::I(1)).is_none());
+    assert_eq!(
+        Value::U64(u64::max_value()).loose_cmp(&Value::F64(1.5)),
+        Some(Ordering::Greater)
+    );
+}
+
+#[test]
+fn compare_instructions() {
+    let func = func!({
+        cmp_eq().op(Value::I(1)).op(Value::F64(1.)),
+        cmp_eq().op(Value::C('a')).op(Value::C('a')),
+        cmp_lt().op(Value::from("a")).op(Value::from("b")),
+        cmp_ne().op(Value::from("a")).op(Value::I(1)),
+        cmp_eq().op(Value::from("a")).op(Value::I(1)),
+        debug(),
+    });
+
+    fn check(data: &mut VmData) -> VmResult {
+        let expected = vec![true, true, true, true, false];
+        assert_eq!(
+            data.vstack,
+            expected.into_iter().map(Value::T).collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    run!(func, check);
+}
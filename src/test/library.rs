@@ -40,7 +40,7 @@ fn fib_function() {
         let frame = data.stack.last_mut().unwrap();
         println!("{:?}", frame);
         let result = data.vstack.pop().expect("no value");
-        assert!(result == value!(21; I64));
+        assert!(result == value!(21; I32));
         Ok(())
     }
 
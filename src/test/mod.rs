@@ -4,9 +4,12 @@ use super::*;
 use crate::gen::*;
 use crate::*;
 
+pub mod compare;
 pub mod library;
+pub mod object;
 pub mod perf;
 pub mod runtime;
+pub mod value;
 
 #[macro_export]
 macro_rules! run {
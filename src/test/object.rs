@@ -0,0 +1,381 @@
+#![cfg(test)]
+use super::*;
+
+fn call(
+    object: &mut dyn ObjectProtocol,
+    data: &mut VmData,
+    name: &str,
+    args: Vec<Value>,
+) -> Result<Option<Value>, String> {
+    let mut ctx = CallContext {
+        data,
+        site: AllocSite::default(),
+    };
+    object.call(&name.to_string(), args, &mut ctx)
+}
+
+fn array_at(data: &VmData, value: Option<Value>) -> Vec<Value> {
+    let handle = match value {
+        Some(Value::Ref(handle)) => handle,
+        other => panic!("expected array but got {:?}", other),
+    };
+    let object = data.obj_pool.get(&handle).unwrap().borrow_mut();
+    object
+        .as_any()
+        .downcast_ref::<Array>()
+        .expect("not an array")
+        .inner()
+        .clone()
+}
+
+#[test]
+fn array_methods() {
+    let mut data = VmData::new();
+    let mut array = Array::new();
+    let ints = |ns: &[i64]| ns.iter().map(|n| Value::I64(*n)).collect::<Vec<_>>();
+
+    call(&mut array, &mut data, "push", ints(&[3, 1, 2])).unwrap();
+    assert_eq!(array.inner(), &ints(&[3, 1, 2]));
+
+    call(&mut array, &mut data, "insert", ints(&[-1, 5])).unwrap();
+    call(&mut array, &mut data, "insert", ints(&[4, 6])).unwrap();
+    assert_eq!(array.inner(), &ints(&[3, 1, 5, 2, 6]));
+    assert!(call(&mut array, &mut data, "insert", ints(&[9, 0])).is_err());
+
+    let removed = call(&mut array, &mut data, "remove", ints(&[0])).unwrap();
+    assert_eq!(removed, Some(Value::I64(3)));
+    let popped = call(&mut array, &mut data, "pop", vec![]).unwrap();
+    assert_eq!(popped, Some(Value::I64(6)));
+    assert_eq!(array.inner(), &ints(&[1, 5, 2]));
+
+    call(&mut array, &mut data, "sort", vec![]).unwrap();
+    assert_eq!(array.inner(), &ints(&[1, 2, 5]));
+    call(&mut array, &mut data, "reverse", vec![]).unwrap();
+    assert_eq!(array.inner(), &ints(&[5, 2, 1]));
+
+    let contains = call(&mut array, &mut data, "contains", vec![Value::F64(2.)]).unwrap();
+    assert_eq!(contains, Some(Value::T(true)));
+    let idx = call(&mut array, &mut data, "index_of", ints(&[1])).unwrap();
+    assert_eq!(idx, Some(Value::from(2usize)));
+    let idx = call(&mut array, &mut data, "index_of", ints(&[7])).unwrap();
+    assert_eq!(idx, Some(Value::Nil));
+
+    let joined = call(&mut array, &mut data, "join", vec![Value::from(", ")]).unwrap();
+    assert_eq!(joined, Some(Value::from("5, 2, 1")));
+
+    let slice = call(&mut array, &mut data, "slice", ints(&[-2])).unwrap();
+    assert_eq!(array_at(&data, slice.clone()), ints(&[2, 1]));
+    let empty = call(&mut array, &mut data, "slice", ints(&[2, 1])).unwrap();
+    assert_eq!(array_at(&data, empty), vec![]);
+
+    let concat = call(&mut array, &mut data, "concat", vec![slice.unwrap()]).unwrap();
+    assert_eq!(array_at(&data, concat), ints(&[5, 2, 1, 2, 1]));
+    let tuple = data
+        .obj_pool
+        .new_tuple_handle(ints(&[4]), AllocSite::default());
+    let concat = call(&mut array, &mut data, "concat", vec![Value::Ref(tuple)]).unwrap();
+    assert_eq!(array_at(&data, concat), ints(&[5, 2, 1, 4]));
+    assert!(call(&mut array, &mut data, "concat", ints(&[4])).is_err());
+
+    call(&mut array, &mut data, "clear", vec![]).unwrap();
+    assert!(array.inner().is_empty());
+    assert!(call(&mut array, &mut data, "pop", vec![]).is_err());
+    assert!(call(&mut array, &mut data, "len", ints(&[1])).is_err());
+}
+
+#[test]
+fn array_indices() {
+    let mut array = Array::from(vec![Value::I64(1), Value::I64(2)]);
+    assert_eq!(array.getk(&Value::I64(-1)), Some(&Value::I64(2)));
+    assert_eq!(array.getk(&Value::I64(-3)), None);
+    array.setk(&Value::I64(-2), Value::I64(0)).unwrap();
+    assert_eq!(array.getk(&Value::I64(0)), Some(&Value::I64(0)));
+
+    let msg = array.setk(&Value::I64(2), Value::Nil).unwrap_err();
+    assert_eq!(msg, "index `2` out of range for array of length 2");
+}
+
+#[test]
+fn array_set_out_of_range() {
+    let mut co = CodeObject::new();
+    co.space.consts.push(Value::I64(3));
+    co.inner = vec![Code::ONewArray, Code::CPush(0), Code::OSet(0)];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    let mut vm = vm::Vm::new();
+    let msg = vm.run(&unit).unwrap_err();
+    assert_eq!(msg, "index `3` out of range for array of length 0");
+    assert_eq!(vm.data.state, VmState::Panic);
+}
+
+#[test]
+fn dict_methods() {
+    let mut data = VmData::new();
+    let mut dict = Dict::new();
+    dict.setk(&Value::from("a"), Value::I64(1)).unwrap();
+    dict.setk(&Value::from("b"), Value::I64(2)).unwrap();
+
+    let keys = call(&mut dict, &mut data, "keys", vec![]).unwrap();
+    let mut keys = array_at(&data, keys);
+    keys.sort();
+    assert_eq!(keys, vec![Value::from("a"), Value::from("b")]);
+    let values = call(&mut dict, &mut data, "values", vec![]).unwrap();
+    let mut values = array_at(&data, values);
+    values.sort();
+    assert_eq!(values, vec![Value::I64(1), Value::I64(2)]);
+
+    let items = call(&mut dict, &mut data, "items", vec![]).unwrap();
+    let items = array_at(&data, items);
+    let mut items = items
+        .into_iter()
+        .map(|item| array_at(&data, Some(item)))
+        .collect::<Vec<_>>();
+    items.sort();
+    assert_eq!(items[0], vec![Value::from("a"), Value::I64(1)]);
+
+    let contains = call(&mut dict, &mut data, "contains", vec![Value::from("a")]).unwrap();
+    assert_eq!(contains, Some(Value::T(true)));
+    // keys are compared strictly
+    let contains = call(&mut dict, &mut data, "contains", vec![Value::I(1)]).unwrap();
+    assert_eq!(contains, Some(Value::T(false)));
+
+    let get = call(&mut dict, &mut data, "get", vec![Value::from("c")]).unwrap();
+    assert_eq!(get, Some(Value::Nil));
+    let get = call(
+        &mut dict,
+        &mut data,
+        "get",
+        vec![Value::from("c"), Value::I64(3)],
+    )
+    .unwrap();
+    assert_eq!(get, Some(Value::I64(3)));
+
+    let removed = call(&mut dict, &mut data, "remove", vec![Value::from("a")]).unwrap();
+    assert_eq!(removed, Some(Value::I64(1)));
+    assert!(call(&mut dict, &mut data, "remove", vec![Value::from("a")]).is_err());
+
+    let mut other = Dict::new();
+    other.setk(&Value::from("b"), Value::I64(4)).unwrap();
+    other.setk(&Value::from("c"), Value::I64(5)).unwrap();
+    let other = data
+        .obj_pool
+        .insert(Rc::new(RefCell::new(other)), AllocSite::default());
+    call(&mut dict, &mut data, "merge", vec![Value::Ref(other)]).unwrap();
+    assert_eq!(dict.getk(&Value::from("b")), Some(&Value::I64(4)));
+    assert_eq!(dict.getk(&Value::from("c")), Some(&Value::I64(5)));
+
+    call(&mut dict, &mut data, "clear", vec![]).unwrap();
+    assert!(dict.inner().is_empty());
+}
+
+#[test]
+fn dict_append() {
+    let mut dict = Dict::new();
+    dict.setk(&Value::I64(1), Value::from("x")).unwrap();
+    dict.append(Value::from("y")).unwrap();
+    dict.append(Value::from("z")).unwrap();
+    assert_eq!(dict.getk(&Value::I64(1)), Some(&Value::from("x")));
+    assert_eq!(dict.getk(&Value::I64(2)), Some(&Value::from("y")));
+    assert_eq!(dict.getk(&Value::I64(3)), Some(&Value::from("z")));
+
+    // keys of other integer widths address the same entries
+    dict.setk(&Value::U8(2), Value::from("w")).unwrap();
+    assert_eq!(dict.getk(&Value::I(2)), Some(&Value::from("w")));
+    assert_eq!(dict.getk(&Value::I32(3)), Some(&Value::from("z")));
+    assert_eq!(dict.inner().len(), 3);
+}
+
+#[test]
+fn dict_order() {
+    let mut data = VmData::new();
+    let mut dict = Dict::new();
+    for key in &["z", "a", "m", "b"] {
+        dict.setk(&Value::from(*key), Value::I64(0)).unwrap();
+    }
+    // overwriting keeps the position, removing shifts the rest
+    dict.setk(&Value::from("a"), Value::I64(1)).unwrap();
+    call(&mut dict, &mut data, "remove", vec![Value::from("m")]).unwrap();
+    dict.append(Value::from("x")).unwrap();
+
+    let keys = call(&mut dict, &mut data, "keys", vec![]).unwrap();
+    assert_eq!(
+        array_at(&data, keys),
+        vec![
+            Value::from("z"),
+            Value::from("a"),
+            Value::from("b"),
+            Value::I64(3),
+        ]
+    );
+    assert_eq!(
+        format!("{:?}", dict),
+        "Dict { entries: {Str(\"z\"): I64(0), Str(\"a\"): I64(1), Str(\"b\"): I64(0), \
+         I64(3): Str(\"x\")}, frozen: false }"
+    );
+}
+
+#[test]
+fn ordered_map_remove() {
+    let mut map = OrderedMap::new();
+    for (i, key) in ["a", "b", "c", "d"].iter().enumerate() {
+        map.insert(Value::from(*key), Value::I64(i as i64));
+    }
+    // removing from the middle keeps the order of the other keys
+    assert_eq!(map.remove(&Value::from("b")), Some(Value::I64(1)));
+    map.insert(Value::from("b"), Value::I64(4));
+    assert_eq!(map.remove(&Value::from("c")), Some(Value::I64(2)));
+    assert_eq!(map.remove(&Value::from("c")), None);
+
+    let keys = map.keys().cloned().collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        vec![Value::from("a"), Value::from("d"), Value::from("b")]
+    );
+    assert_eq!(map.get(&Value::from("a")), Some(&Value::I64(0)));
+    assert_eq!(map.get(&Value::from("d")), Some(&Value::I64(3)));
+    assert_eq!(map.get(&Value::from("b")), Some(&Value::I64(4)));
+    assert_eq!(map.len(), 3);
+}
+
+#[test]
+fn set_methods() {
+    let mut data = VmData::new();
+    let ints = |ns: &[i64]| ns.iter().map(|n| Value::I64(*n)).collect::<Vec<_>>();
+    let mut set = Set::from(ints(&[1, 2, 3]));
+
+    let added = call(&mut set, &mut data, "add", ints(&[2])).unwrap();
+    assert_eq!(added, Some(Value::T(false)));
+    let added = call(&mut set, &mut data, "add", ints(&[4])).unwrap();
+    assert_eq!(added, Some(Value::T(true)));
+    call(&mut set, &mut data, "remove", ints(&[1])).unwrap();
+    assert!(call(&mut set, &mut data, "remove", ints(&[1])).is_err());
+    let contains = call(&mut set, &mut data, "contains", ints(&[3])).unwrap();
+    assert_eq!(contains, Some(Value::T(true)));
+    let len = call(&mut set, &mut data, "len", vec![]).unwrap();
+    assert_eq!(len, Some(Value::from(3usize)));
+
+    let other = Set::from(ints(&[3, 4, 5]));
+    let other = data
+        .obj_pool
+        .insert(Rc::new(RefCell::new(other)), AllocSite::default());
+    let set_at = |data: &VmData, value: Option<Value>| match value {
+        Some(Value::Ref(handle)) => {
+            let object = data.obj_pool.get(&handle).unwrap().borrow_mut();
+            let set = object.as_any().downcast_ref::<Set>().expect("not a set");
+            set.inner().keys().cloned().collect::<Vec<_>>()
+        }
+        other => panic!("expected set but got {:?}", other),
+    };
+    let args = vec![Value::Ref(other)];
+    let union = call(&mut set, &mut data, "union", args.clone()).unwrap();
+    assert_eq!(set_at(&data, union), ints(&[2, 3, 4, 5]));
+    let intersection = call(&mut set, &mut data, "intersection", args.clone()).unwrap();
+    assert_eq!(set_at(&data, intersection), ints(&[3, 4]));
+    let difference = call(&mut set, &mut data, "difference", args).unwrap();
+    assert_eq!(set_at(&data, difference), ints(&[2]));
+    assert_eq!(set.inner().len(), 3);
+}
+
+#[test]
+fn tuple_methods() {
+    let mut data = VmData::new();
+    let ints = |ns: &[i64]| ns.iter().map(|n| Value::I64(*n)).collect::<Vec<_>>();
+    let tuple = |data: &mut VmData, items: &[i64]| {
+        Value::Ref(
+            data.obj_pool
+                .new_tuple_handle(ints(items), AllocSite::default()),
+        )
+    };
+    let receiver = tuple(&mut data, &[1, 2]);
+    let other = tuple(&mut data, &[3]);
+    let array = Array::from(ints(&[4]));
+    let array = data
+        .obj_pool
+        .insert(Rc::new(RefCell::new(array)), AllocSite::default());
+
+    let object = match &receiver {
+        Value::Ref(handle) => data.obj_pool.get(handle).unwrap().clone(),
+        _ => unreachable!(),
+    };
+    let mut object = object.borrow_mut();
+    // results are interned like every other tuple
+    let result = call(&mut *object, &mut data, "concat", vec![other]).unwrap();
+    assert_eq!(result, Some(tuple(&mut data, &[1, 2, 3])));
+    let result = call(&mut *object, &mut data, "concat", vec![Value::Ref(array)]).unwrap();
+    assert_eq!(result, Some(tuple(&mut data, &[1, 2, 4])));
+    let result = call(&mut *object, &mut data, "concat", vec![receiver.clone()]).unwrap();
+    assert_eq!(result, Some(tuple(&mut data, &[1, 2, 1, 2])));
+    let result = call(&mut *object, &mut data, "slice", ints(&[-1])).unwrap();
+    assert_eq!(result, Some(tuple(&mut data, &[2])));
+    assert!(call(&mut *object, &mut data, "concat", ints(&[1])).is_err());
+}
+
+#[test]
+fn frozen_objects() {
+    let mut data = VmData::new();
+    let mut dict = Dict::new();
+    dict.setk(&Value::from("a"), Value::I64(1)).unwrap();
+    dict.freeze().unwrap();
+    assert!(dict.is_frozen());
+    assert!(dict.setk(&Value::from("b"), Value::I64(2)).is_err());
+    let msg = call(&mut dict, &mut data, "clear", vec![]).unwrap_err();
+    assert_eq!(msg, "cannot modify frozen dict");
+    // reading is still possible
+    let len = call(&mut dict, &mut data, "len", vec![]).unwrap();
+    assert_eq!(len, Some(Value::from(1usize)));
+
+    let a = data
+        .obj_pool
+        .new_tuple_handle(vec![Value::I64(1)], AllocSite::default());
+    let b = data
+        .obj_pool
+        .new_tuple_handle(vec![Value::I64(1)], AllocSite::default());
+    let c = data
+        .obj_pool
+        .new_tuple_handle(vec![Value::I64(2)], AllocSite::default());
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+    let mut tuple = data.obj_pool.get(&a).unwrap().borrow_mut();
+    let tuple = tuple.as_indexable().unwrap();
+    assert_eq!(tuple.getk(&Value::I64(0)), Some(&Value::I64(1)));
+    assert_eq!(
+        tuple.append(Value::I64(2)).unwrap_err(),
+        "cannot modify tuple"
+    );
+}
+
+#[test]
+fn printing() {
+    let mut data = VmData::new();
+    let site = AllocSite::default;
+    let alloc =
+        |data: &mut VmData, object: ObjectRef| Value::Ref(data.obj_pool.insert(object, site()));
+
+    let mut dict = Dict::new();
+    dict.setk(&Value::from("b"), Value::I64(2)).unwrap();
+    let dict = alloc(&mut data, Rc::new(RefCell::new(dict)));
+    let tuple = Value::Ref(data.obj_pool.new_tuple_handle(vec![Value::I64(1)], site()));
+    let set = alloc(&mut data, Rc::new(RefCell::new(Set::new())));
+    let array = Array::from(vec![Value::I64(1), Value::from("a"), dict, tuple, set]);
+    let array = alloc(&mut data, Rc::new(RefCell::new(array)));
+    assert_eq!(data.obj_pool.display(&array), "[1, a, {b: 2}, (1,), set()]");
+    assert_eq!(
+        data.obj_pool.repr(&array),
+        "[1, \"a\", {\"b\": 2}, (1,), set()]"
+    );
+
+    // user objects print their fields; cycles are cut
+    data.units.load_ty(&Unit::new(), "Point".into()).unwrap();
+    let chain = data.units.type_chain(&"Point".to_string()).unwrap();
+    let point = data.obj_pool.new_handle_with_type(chain, site());
+    {
+        let mut object = data.obj_pool.get(&point).unwrap().borrow_mut();
+        let object = object.as_indexable().unwrap();
+        object.setk(&Value::from("y"), Value::from("z")).unwrap();
+        object.setk(&Value::from("x"), Value::Ref(point)).unwrap();
+    }
+    assert_eq!(
+        data.obj_pool.repr(&Value::Ref(point)),
+        "Point { x: ..., y: \"z\" }"
+    );
+}
@@ -1,6 +1,8 @@
 #![cfg(test)]
 use super::*;
 
+use std::convert::TryFrom;
+
 #[test]
 fn allocation() {
     let func = func!({
@@ -13,7 +15,13 @@ fn allocation() {
     });
 
     fn has_oref(data: &mut VmData) -> VmResult {
-        assert!(*data.vstack.last().unwrap() == value!(2; Ref));
+        // the array reuses the slot of the disposed object
+        let handle = match data.vstack.last().unwrap() {
+            Value::Ref(handle) => *handle,
+            _ => panic!("no object ref"),
+        };
+        assert_eq!(handle_slot(handle), 1);
+        assert_eq!(handle_generation(handle), 1);
         Ok(())
     }
 
@@ -38,9 +46,9 @@ fn new_dict() {
         assert!(*data.vstack.last().unwrap() == Value::Ref(1));
         let dict = &mut data.obj_pool.get_mut(&1).expect("no object").borrow_mut();
         let dict = dict.as_indexable().expect("not indexable");
-        assert_eq!(dict.getk(&Value::from("x")).unwrap(), &Value::I64(10));
-        assert_eq!(dict.getk(&Value::from("y")).unwrap(), &Value::I64(10));
-        assert_eq!(dict.getk(&Value::from(10)).unwrap(), &Value::I64(11));
+        assert_eq!(dict.getk(&Value::from("x")).unwrap(), &Value::I32(10));
+        assert_eq!(dict.getk(&Value::from("y")).unwrap(), &Value::I32(10));
+        assert_eq!(dict.getk(&Value::from(10)).unwrap(), &Value::I32(11));
         Ok(())
     }
 
@@ -64,9 +72,1291 @@ fn object_call() {
 
     fn check_content(data: &mut VmData) -> VmResult {
         // expect len for 3 items
-        assert!(*data.vstack.last().unwrap() == Value::Ref(3));
+        assert!(*data.vstack.last().unwrap() == Value::U64(3));
         Ok(())
     }
 
     run!(func, check_content);
 }
+
+#[test]
+fn nil_values() {
+    let unit = unit! {
+        noop => func!({
+            ass().var("x").op(1),
+        }),
+        main => func!({
+            // `y` is a local but was not assigned yet
+            push().var("y"),
+            call("noop"),
+            debug(),
+            ass().var("y").op(1),
+        }),
+    };
+
+    fn check_nil(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack, vec![Value::Nil, Value::Nil]);
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_nil);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn missing_key() {
+    let mut co = CodeObject::new();
+    co.space.consts.push(Value::from("missing"));
+    co.inner = vec![
+        Code::ONewDict,
+        Code::OGet(0),
+        Code::Int(vm::Interrupt::Debug as usize),
+    ];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    fn check_nil(data: &mut VmData) -> VmResult {
+        assert_eq!(*data.vstack.last().unwrap(), Value::Nil);
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_nil);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn overflow_error() {
+    let func = func!({
+        add().op(Value::I(127)).op(Value::I(1)),
+    });
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+
+    let mut vm = vm::Vm::new();
+    assert!(vm.run(&unit).is_err());
+    assert_eq!(vm.data.state, VmState::Panic);
+
+    let mut vm = vm::Vm::new();
+    vm.data.arithmetic = ArithmeticMode::Wrapping;
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn overflow_per_object() {
+    let mut func = CodeBuilder::new();
+    func.with_arithmetic(ArithmeticMode::Promote)
+        .step(mul().op(Value::I(100)).op(Value::I(100)).end())
+        .debug();
+    let func = func.build(true).unwrap();
+
+    fn check_promoted(data: &mut VmData) -> VmResult {
+        assert_eq!(*data.vstack.last().unwrap(), Value::I64(10000));
+        Ok(())
+    }
+
+    run!(func, check_promoted);
+}
+
+#[test]
+fn type_instructions() {
+    let func = func!({
+        type_of().op(Value::U16(1)),
+        is_type(ValueType::I32).op(10),
+        cast(ValueType::F32).op(Value::I(3)),
+        assert_type(ValueType::Str).op("text"),
+        debug(),
+    });
+
+    fn check(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::from("u16"),
+                Value::T(true),
+                Value::F32(3.),
+                Value::from("text"),
+            ]
+        );
+        Ok(())
+    }
+
+    run!(func, check);
+
+    let func = func!({
+        assert_type(ValueType::Str).op(1),
+    });
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    assert!(vm.run(&unit).is_err());
+}
+
+#[test]
+fn type_dispatch() {
+    let mut func = CodeBuilder::new();
+    func.with_params(vec!["x"])
+        .branch_if_type("x", ValueType::Str, vec![ret().op("string").end()])
+        .branch_if_type("x", ValueType::I32, vec![ret().op("number").end()])
+        .step(ret().op("other").end());
+
+    let unit = unit! {
+        describe => func.build(true).unwrap(),
+        main => func!({
+            call("describe").op("a"),
+            call("describe").op(1),
+            call("describe").op(Value::T(true)),
+            debug(),
+        }),
+    };
+
+    fn check(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::from("string"),
+                Value::from("number"),
+                Value::from("other")
+            ]
+        );
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn handle_separation() {
+    // lengths are unsigned numbers and can not be disposed
+    let func = func!({
+        onewarray(),
+        ocall("len"),
+        odispose(),
+    });
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    assert!(vm.run(&unit).is_err());
+
+    // handles do not take part in arithmetic
+    let func = func!({
+        add().op(onewarray()).op(1),
+    });
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    assert!(vm.run(&unit).is_err());
+
+    // object instructions require a handle
+    let func = func!({
+        push().op(1),
+        oappend().op(2),
+    });
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    assert!(vm.run(&unit).is_err());
+}
+
+#[test]
+fn collect_unreachable() {
+    let mut co = CodeObject::new();
+    co.space.locals.push("keep".into());
+    co.inner = vec![
+        // `keep` holds an array which holds a dict
+        Code::ONewArray,
+        Code::ONewDict,
+        Code::OAppend,
+        Code::LPop(0),
+        // overwriting `keep` makes the first pair unreachable
+        Code::ONewArray,
+        Code::ONewDict,
+        Code::OAppend,
+        Code::LPop(0),
+        Code::Int(vm::Interrupt::Debug as usize),
+    ];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    fn collect(data: &mut VmData) -> VmResult {
+        assert_eq!(data.obj_pool.stats().live, 4);
+        assert_eq!(data.collect_garbage(), 2);
+        let stats = data.obj_pool.stats();
+        assert_eq!(stats.live, 2);
+        assert_eq!(stats.allocated, 4);
+        assert_eq!(stats.freed, 2);
+        assert_eq!(stats.collections, 1);
+        assert!(data.obj_pool.get(&3).is_some());
+        assert!(data.obj_pool.get(&4).is_some());
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &collect);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn collect_on_allocation() {
+    let mut co = CodeObject::new();
+    co.space.locals.push("tmp".into());
+    for _ in 0..4 * vm::VM_GC_THRESHOLD {
+        co.inner.push(Code::ONewArray);
+        co.inner.push(Code::LPop(0));
+    }
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    let mut vm = vm::Vm::new();
+    vm.run(&unit).expect("error in code");
+
+    let stats = vm.heap_stats();
+    assert_eq!(stats.allocated, 4 * vm::VM_GC_THRESHOLD);
+    assert!(0 < stats.collections);
+    assert!(stats.live <= vm::VM_GC_THRESHOLD);
+}
+
+#[test]
+fn dispose_reachable() {
+    let mut co = CodeObject::new();
+    co.space.locals.push("arr".into());
+    co.inner = vec![
+        Code::ONewArray,
+        Code::Dup,
+        Code::LPop(0),
+        // still referenced by `arr`; the hint must be ignored
+        Code::ODispose,
+        Code::LPush(0),
+        Code::Int(vm::Interrupt::Debug as usize),
+    ];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    fn check_alive(data: &mut VmData) -> VmResult {
+        assert_eq!(*data.vstack.last().unwrap(), Value::Ref(1));
+        assert!(data.obj_pool.get(&1).is_some());
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_alive);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn slot_reuse() {
+    let mut pool = ObjectPool::new();
+    let first = pool.new_array_handle(AllocSite::new(Some("main".into()), 3));
+    pool.dispose_handle(&first);
+    let second = pool.new_dict_handle(AllocSite::default());
+
+    assert_eq!(handle_slot(first), handle_slot(second));
+    assert_ne!(first, second);
+    assert!(pool.get(&first).is_none());
+    assert!(pool.get(&second).is_some());
+    assert_eq!(pool.len(), 1);
+
+    let msg = pool.resolve(&first).unwrap_err();
+    assert!(msg.contains("use after dispose"));
+    assert!(msg.contains("`main` at instruction 3"));
+}
+
+#[test]
+fn use_after_dispose() {
+    let mut co = CodeObject::new();
+    co.space.consts.push(Value::from("len"));
+    co.space.consts.push(Value::U64(0));
+    co.inner = vec![
+        Code::ONewArray,
+        // the host releases the array while it is still on the stack
+        Code::Int(vm::Interrupt::Debug as usize),
+        Code::CPush(1),
+        Code::OCall(0),
+    ];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    fn dispose(data: &mut VmData) -> VmResult {
+        data.obj_pool.dispose_handle(&1);
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &dispose);
+    let msg = vm.run(&unit).unwrap_err();
+    assert!(msg.contains("use after dispose of object `1`"));
+    assert!(msg.contains("instruction 0"));
+    assert_eq!(vm.data.state, VmState::Panic);
+}
+
+#[test]
+fn invalid_argc() {
+    let mut co = CodeObject::new();
+    co.space.consts.push(Value::from("len"));
+    co.space.consts.push(Value::Nil);
+    co.inner = vec![Code::ONewArray, Code::CPush(1), Code::OCall(0)];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    let mut vm = vm::Vm::new();
+    let msg = vm.run(&unit).unwrap_err();
+    assert!(msg.contains("invalid argument count"));
+    assert_eq!(vm.data.state, VmState::Panic);
+
+    // negative counts are rejected instead of wrapping around
+    for argc in vec![Value::I(-1), Value::I64(-1), Value::F64(-1.)] {
+        let mut co = CodeObject::new();
+        co.space.consts.push(Value::from("len"));
+        co.space.consts.push(argc);
+        co.inner = vec![Code::ONewArray, Code::CPush(1), Code::OCall(0)];
+        let unit = UnitBuilder::from_object(co).build().unwrap();
+        let mut vm = vm::Vm::new();
+        let msg = vm.run(&unit).unwrap_err();
+        assert!(msg.contains("invalid argument count"));
+    }
+    assert!(usize::try_from(Value::F64(1.5)).is_err());
+
+    // the receiver slot must not overflow the count
+    let mut co = CodeObject::new();
+    co.space.consts.push(Value::from("len"));
+    co.space.consts.push(Value::U64(u64::MAX));
+    co.inner = vec![Code::ONewArray, Code::CPush(1), Code::OCall(0)];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+    let mut vm = vm::Vm::new();
+    let msg = vm.run(&unit).unwrap_err();
+    assert!(msg.contains("not enough arguments"));
+}
+
+#[test]
+fn clone_heap() {
+    let func = func!({
+        onewdict(),
+        oset().op("x").op(10),
+        debug(),
+    });
+
+    fn fork(data: &mut VmData) -> VmResult {
+        let handle = data.vstack.last().unwrap().clone();
+        let mut fork = data.clone();
+        assert_eq!(fork.vstack.last(), Some(&handle));
+        assert_eq!(fork.obj_pool.stats(), data.obj_pool.stats());
+
+        let id = match handle {
+            Value::Ref(id) => id,
+            _ => panic!("no object ref"),
+        };
+        {
+            let mut dict = fork.obj_pool.get(&id).unwrap().borrow_mut();
+            let dict = dict.as_indexable().unwrap();
+            dict.setk(&Value::from("x"), Value::I32(11)).unwrap();
+        }
+
+        let mut dict = data.obj_pool.get(&id).unwrap().borrow_mut();
+        let dict = dict.as_indexable().unwrap();
+        assert_eq!(dict.getk(&Value::from("x")), Some(&Value::I32(10)));
+        Ok(())
+    }
+
+    run!(func, fork);
+}
+
+#[test]
+fn object_fields() {
+    let mut co = CodeObject::new();
+    co.space.globals.push("object".into());
+    co.space.consts.push(Value::from("x"));
+    co.space.consts.push(Value::I64(10));
+    co.space.consts.push(Value::from("y"));
+    co.space.locals.push("x".into());
+    co.inner = vec![
+        Code::ONew(0),
+        Code::CPush(1),
+        Code::OSet(0),
+        Code::OGet(0),
+        Code::Int(vm::Interrupt::Debug as usize),
+        Code::LPop(0),
+        Code::OGet(2),
+    ];
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    fn check_field(data: &mut VmData) -> VmResult {
+        assert_eq!(*data.vstack.last().unwrap(), Value::I64(10));
+        let handle = match data.vstack[0] {
+            Value::Ref(handle) => handle,
+            _ => panic!("no object ref"),
+        };
+        let object = data.obj_pool.get(&handle).unwrap().borrow_mut();
+        assert_eq!(
+            format!("{:?}", object),
+            "Object { ty: \"object\", fields: {\"x\": I64(10)} }"
+        );
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_field);
+    let msg = vm.run(&unit).unwrap_err();
+    assert_eq!(msg, "object has no field `y`");
+}
+
+#[test]
+fn method_self() {
+    let ty = unit! {
+        store => func!([self, n] => {
+            push().var("self"),
+            oset().op("n").var("n"),
+            add().var("n").op(1),
+            ret(),
+        }),
+    };
+    let main = unit! {
+        main => func!({
+            onew("Counter"),
+            pop().var("c"),
+            push().var("c"),
+            ocall("store").op(5),
+            push().var("c"),
+            debug(),
+        }),
+    };
+
+    fn check_store(data: &mut VmData) -> VmResult {
+        let handle = match data.vstack.pop().unwrap() {
+            Value::Ref(handle) => handle,
+            _ => panic!("no object ref"),
+        };
+        assert_eq!(*data.vstack.last().unwrap(), Value::I32(6));
+        let mut object = data.obj_pool.get(&handle).unwrap().borrow_mut();
+        let object = object.as_indexable().unwrap();
+        assert_eq!(object.getk(&Value::from("n")), Some(&Value::I32(5)));
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.data.units.load_ty(&ty, "Counter".into()).unwrap();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_store);
+    vm.run(&main).expect("error in code");
+}
+
+#[derive(Clone, Debug)]
+struct Accumulator(Value);
+
+impl ObjectProtocol for Accumulator {
+    fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
+        match key.to_string().as_ref() {
+            "add" | "wrap" | "__add__" => Some(ObjectMethod::Native),
+            _ => None,
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &Name,
+        args: Vec<Value>,
+        ctx: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        match name.as_ref() {
+            "add" | "__add__" => {
+                for arg in args.iter() {
+                    self.0 = self.0.add(arg).map_err(|e| e.to_string())?;
+                }
+                Ok(Some(self.0.clone()))
+            }
+            "wrap" => {
+                let handle = ctx.data.obj_pool.new_array_handle(ctx.site.clone());
+                Ok(Some(Value::Ref(handle)))
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+}
+
+#[test]
+fn native_params() {
+    let mut co = CodeObject::new();
+    co.space.consts.push(Value::from("add"));
+    co.space.consts.push(Value::I64(2));
+    co.space.consts.push(Value::I64(3));
+    co.space.consts.push(Value::I64(4));
+    co.space.consts.push(Value::from("wrap"));
+    co.space.consts.push(Value::I64(0));
+    co.space.consts.push(Value::from("len"));
+    co.inner = vec![
+        Code::GPush(0),
+        Code::CPush(2),
+        Code::CPush(3),
+        Code::CPush(1),
+        Code::OCall(0),
+        Code::GPush(0),
+        Code::CPush(5),
+        Code::OCall(4),
+        Code::Int(vm::Interrupt::Debug as usize),
+        // unknown methods are reported
+        Code::GPush(0),
+        Code::CPush(5),
+        Code::OCall(6),
+    ];
+    co.space.globals.push("acc".into());
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    fn check_result(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack[0], Value::I64(8));
+        let handle = match data.vstack[1] {
+            Value::Ref(handle) => handle,
+            _ => panic!("no object ref"),
+        };
+        assert_eq!(data.obj_pool.site(&handle).unwrap().ip, 7);
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    let acc = Rc::new(RefCell::new(Accumulator(Value::I64(1))));
+    let handle = vm.data.obj_pool.insert(acc, AllocSite::default());
+    vm.data.globals.insert("acc".into(), Value::Ref(handle));
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_result);
+    let msg = vm.run(&unit).unwrap_err();
+    assert_eq!(msg, "method `len` not found");
+}
+
+#[test]
+fn for_each_array() {
+    let mut func = CodeBuilder::new();
+    func.step(ass().var("sum").op(0).end())
+        .for_each(
+            "x",
+            vec![1, 2, 3],
+            vec![ass().var("sum").op(add().var("sum").var("x").end()).end()],
+        )
+        .step(push().var("sum").end())
+        .debug();
+    let func = func.build(true).unwrap();
+
+    fn check_sum(data: &mut VmData) -> VmResult {
+        // the iterator is gone after the loop
+        assert_eq!(data.vstack, vec![Value::I32(6)]);
+        Ok(())
+    }
+
+    run!(func, check_sum);
+}
+
+#[test]
+fn for_each_dict() {
+    let mut func = CodeBuilder::new();
+    func.step(ass().var("n").op(0).end())
+        .for_each(
+            "key",
+            onewdict()
+                .op(oset().op("a").op(1).op("b").op(2).end())
+                .end(),
+            vec![ass().var("n").op(add().var("n").op(1).end()).end()],
+        )
+        .step(push().var("n").end())
+        .debug();
+    let func = func.build(true).unwrap();
+
+    fn check_keys(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack, vec![Value::I32(2)]);
+        Ok(())
+    }
+
+    run!(func, check_keys);
+}
+
+#[test]
+fn for_each_virtual() {
+    // counts the global `n` down to zero
+    let ty = unit! {
+        __iter__ => func!([self] => {
+            push().var("self"),
+            ret(),
+        }),
+        __next__ => func!([self] => {
+            cmp_eq().var("n").op(0) => {
+                ret(),
+            },
+            sub().var("n").op(1),
+            pop().var("n"),
+            push().var("n"),
+            ret(),
+        }),
+    };
+    let mut main = CodeBuilder::new();
+    main.step(ass().var("sum").op(0).end())
+        .for_each(
+            "x",
+            onew("Countdown"),
+            vec![ass().var("sum").op(add().var("sum").var("x").end()).end()],
+        )
+        .step(push().var("sum").end())
+        .debug();
+    let mut unit = UnitBuilder::new();
+    unit.decl("main", main.build(true).unwrap());
+    let unit = unit.build().unwrap();
+
+    fn check_sum(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack, vec![Value::I32(3)]);
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.data.globals.insert("n".into(), Value::I32(3));
+    vm.data.units.load_ty(&ty, "Countdown".into()).unwrap();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_sum);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn computed_keys() {
+    let mut func = CodeBuilder::new();
+    func.step(ass().var("arr").op(vec![10, 20, 30]).end())
+        .step(ass().var("sum").op(0).end())
+        .for_each(
+            "i",
+            vec![0, 1, 2],
+            vec![ass()
+                .var("sum")
+                .op(add()
+                    .var("sum")
+                    .op(push().var("arr").op(oget().var("i").end()).end())
+                    .end())
+                .end()],
+        )
+        .step(push().var("sum").end())
+        // negative constant key
+        .step(push().var("arr").op(oget().op(-1).end()).end())
+        // dict lookup by variable
+        .step(ass().var("key").op("a").end())
+        .step(
+            push()
+                .op(onewdict().end())
+                .op(oset().var("key").op(5).end())
+                .op(oget().var("key").end())
+                .end(),
+        )
+        // chained keys i.e. `d["arr"][1]`
+        .step(ass().var("d").op(onewdict().end()).end())
+        .step(push().var("d").op(oset().op("arr").var("arr").end()).end())
+        .step(pop().var("d").end())
+        .step(push().var("d").op(oget().op("arr").op(1).end()).end())
+        .debug();
+    let func = func.build(true).unwrap();
+
+    fn check_values(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::I32(60),
+                Value::I32(30),
+                Value::I32(5),
+                Value::I32(20)
+            ]
+        );
+        Ok(())
+    }
+
+    run!(func, check_values);
+}
+
+#[test]
+fn dict_append_get() {
+    // appended values are read back with the integer literals of the builder
+    let func = func!({
+        ass().var("d").op(onewdict().end()),
+        push().var("d").op(oappend().op("x").end()).op(oappend().op("y").end()),
+        pop().var("d"),
+        push().var("d").op(oget().op(0).end()),
+        push().var("d").op(oget().op(Value::I(1)).end()),
+        debug(),
+    });
+
+    fn check(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack, vec![Value::from("x"), Value::from("y")]);
+        Ok(())
+    }
+
+    run!(func, check);
+}
+
+#[test]
+fn object_ops_in_branch() {
+    let func = func!({
+        ass().var("d").op(vec![(Some("a"), "b")]),
+        cmp_eq().op(1).op(1) => {
+            push().var("d").op(oget().op("a").end()),
+            debug(),
+        },
+    });
+
+    fn check_value(data: &mut VmData) -> VmResult {
+        assert_eq!(*data.vstack.last().unwrap(), Value::from("b"));
+        Ok(())
+    }
+
+    run!(func, check_value);
+}
+
+#[test]
+fn inheritance() {
+    let animal = unit! {
+        name => func!([self] => {
+            ret().op("animal"),
+        }),
+        legs => func!([self] => {
+            ret().op(4),
+        }),
+    };
+    let dog = unit! {
+        name => func!([self] => {
+            ret().op("dog"),
+        }),
+        parent => func!([self] => {
+            push().var("self"),
+            osuper("name"),
+            ret(),
+        }),
+    };
+    // inherits `parent` from dog which must still resolve to animal's `name`
+    let puppy = unit! {
+        name => func!([self] => {
+            ret().op("puppy"),
+        }),
+    };
+    let main = unit! {
+        main => func!({
+            ass().var("p").op(onew("Puppy").end()),
+            push().var("p"),
+            ocall("name"),
+            push().var("p"),
+            ocall("parent"),
+            push().var("p"),
+            ocall("legs"),
+            push().op(instance_of("Animal").var("p").end()),
+            push().op(instance_of("Puppy").op(onew("Dog").end()).end()),
+            push().op(instance_of("Animal").op(onewarray().end()).end()),
+            debug(),
+        }),
+    };
+
+    fn check_methods(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::from("puppy"),
+                Value::from("animal"),
+                Value::I32(4),
+                Value::T(true),
+                Value::T(false),
+                Value::T(false),
+            ]
+        );
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.data.units.load_ty(&animal, "Animal".into()).unwrap();
+    vm.data
+        .units
+        .load_ty_derived(&dog, "Dog".into(), "Animal".into())
+        .unwrap();
+    vm.data
+        .units
+        .load_ty_derived(&puppy, "Puppy".into(), "Dog".into())
+        .unwrap();
+    assert!(vm
+        .data
+        .units
+        .load_ty_derived(&puppy, "Cat".into(), "Unknown".into())
+        .is_err());
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_methods);
+    vm.run(&main).expect("error in code");
+}
+
+#[test]
+fn reload_type() {
+    let animal = unit! {
+        legs => func!([self] => {
+            ret().op(4),
+        }),
+    };
+    let mut dog = UnitBuilder::new();
+    dog.derive("Animal").set(
+        "name",
+        func!([self] => {
+            ret().op("dog"),
+        }),
+    );
+    let dog = dog.build().unwrap();
+    let main = unit! {
+        main => func!({
+            push().op(onew("Dog").end()),
+            ocall("legs"),
+            debug(),
+        }),
+    };
+
+    fn check_legs(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack, vec![Value::I32(4)]);
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.data.units.load_ty(&animal, "Animal".into()).unwrap();
+    vm.data.units.load_ty(&dog, "Dog".into()).unwrap();
+    assert_eq!(
+        vm.data.units.parent_ty(&"Dog".to_string()),
+        Some("Animal".to_string())
+    );
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_legs);
+    vm.run(&main).expect("error in code");
+
+    // the reloaded type does not declare a parent anymore
+    let dog = unit! {
+        name => func!([self] => {
+            ret().op("dog"),
+        }),
+    };
+    let mut vm = vm::Vm::new();
+    vm.data.units.load_ty(&animal, "Animal".into()).unwrap();
+    vm.data
+        .units
+        .load_ty_derived(&dog, "Dog".into(), "Animal".into())
+        .unwrap();
+    vm.data.units.load_ty(&dog, "Dog".into()).unwrap();
+    assert_eq!(vm.data.units.parent_ty(&"Dog".to_string()), None);
+    let msg = vm.run(&main).unwrap_err();
+    assert!(msg.contains("legs"));
+}
+
+#[test]
+fn operator_overloading() {
+    let money = unit! {
+        __add__ => func!([self, other] => {
+            ret().op(add()
+                .op(push().var("self").op(oget().op("v").end()).end())
+                .op(push().var("other").op(oget().op("v").end()).end())
+                .end()),
+        }),
+        __radd__ => func!([self, other] => {
+            ret().op(add()
+                .var("other")
+                .op(push().var("self").op(oget().op("v").end()).end())
+                .end()),
+        }),
+        __lt__ => func!([self, other] => {
+            ret().op(cmp_lt()
+                .op(push().var("self").op(oget().op("v").end()).end())
+                .op(push().var("other").op(oget().op("v").end()).end())
+                .end()),
+        }),
+        __eq__ => func!([self, other] => {
+            ret().op(cmp_eq()
+                .op(push().var("self").op(oget().op("v").end()).end())
+                .op(push().var("other").op(oget().op("v").end()).end())
+                .end()),
+        }),
+    };
+    let main = unit! {
+        main => func!({
+            ass().var("a").op(onew("Money").end()),
+            push().var("a"),
+            oset().op("v").op(3),
+            pop().var("a"),
+            ass().var("b").op(onew("Money").end()),
+            push().var("b"),
+            oset().op("v").op(4),
+            pop().var("b"),
+            push().op(add().var("a").var("b").end()),
+            push().op(add().op(10).var("a").end()),
+            push().op(cmp_lt().var("a").var("b").end()),
+            // reflected to `b.__lt__(a)`
+            push().op(cmp_gt().var("a").var("b").end()),
+            // negation of `__eq__`
+            push().op(cmp_ne().var("a").var("a").end()),
+            debug(),
+            push().op(mul().var("a").op(2).end()),
+        }),
+    };
+
+    fn check_results(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::I32(7),
+                Value::I32(13),
+                Value::T(true),
+                Value::T(false),
+                Value::T(false),
+            ]
+        );
+        Ok(())
+    }
+
+    let mut vm = vm::Vm::new();
+    vm.data.units.load_ty(&money, "Money".into()).unwrap();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_results);
+    let msg = vm.run(&main).unwrap_err();
+    assert_eq!(
+        msg,
+        "operator is not defined: no method `__mul__` on type `Money`"
+    );
+}
+
+#[test]
+fn compare_non_bool() {
+    let weird = unit! {
+        __eq__ => func!([self, other] => {
+            ret().op(1),
+        }),
+        __lt__ => func!([self, other] => {
+            ret().op("less"),
+        }),
+    };
+
+    for mut cmp in vec![cmp_eq(), cmp_ne(), cmp_lt()] {
+        let main = unit! {
+            main => func!({
+                push().op(cmp.op(onew("Weird").end()).op(1).end()),
+            }),
+        };
+        let mut vm = vm::Vm::new();
+        vm.data.units.load_ty(&weird, "Weird".into()).unwrap();
+        let msg = vm.run(&main).unwrap_err();
+        assert!(msg.contains("must return a boolean"));
+        assert_eq!(vm.data.state, VmState::Panic);
+    }
+}
+
+#[test]
+fn compare_identity() {
+    // objects without `__eq__` are compared by their handle
+    let func = func!({
+        ass().var("a").op(onewarray().end()),
+        push().op(cmp_eq().var("a").var("a").end()),
+        push().op(cmp_eq().var("a").op(onewarray().end()).end()),
+        push().op(cmp_eq().var("a").op(Value::Nil).end()),
+        push().op(cmp_ne().op(Value::Nil).var("a").end()),
+        push().op(cmp_eq().op(onewdict().end()).op(Value::Nil).end()),
+        debug(),
+    });
+
+    fn check(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::T(true),
+                Value::T(false),
+                Value::T(false),
+                Value::T(true),
+                Value::T(false),
+            ]
+        );
+        Ok(())
+    }
+
+    run!(func, check);
+
+    // ordering still requires the operator method
+    let func = func!({
+        ass().var("a").op(onewarray().end()),
+        push().op(cmp_lt().var("a").var("a").end()),
+    });
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    let msg = vm.run(&unit).unwrap_err();
+    assert!(msg.contains("__lt__"));
+}
+
+#[test]
+fn native_operator() {
+    let func = func!({
+        push().op(add().var("acc").op(5).end()),
+        push().op(sub().var("acc").op(1).end()),
+    });
+
+    let mut vm = vm::Vm::new();
+    let acc = Rc::new(RefCell::new(Accumulator(Value::I64(1))));
+    let handle = vm.data.obj_pool.insert(acc, AllocSite::default());
+    vm.data.globals.insert("acc".into(), Value::Ref(handle));
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let msg = vm.run(&unit).unwrap_err();
+    assert_eq!(vm.data.vstack, vec![Value::I64(6)]);
+    assert_eq!(
+        msg,
+        format!(
+            "operator is not defined: no method `__sub__` on object `{}`",
+            handle
+        )
+    );
+}
+
+#[test]
+fn for_each_set() {
+    let mut func = CodeBuilder::new();
+    func.step(
+        ass()
+            .var("s")
+            .op(onewset().op(1).op(2).op(2).op(3).end())
+            .end(),
+    )
+    .step(push().var("s").end())
+    .step(ocall("add").op(3).end())
+    .step(pop().var("added").end())
+    .step(ass().var("sum").op(0).end())
+    .for_each(
+        "x",
+        Operation::push().var("s").end(),
+        vec![ass().var("sum").op(add().var("sum").var("x").end()).end()],
+    )
+    .step(push().var("sum").end())
+    .step(push().var("added").end())
+    .debug();
+    let func = func.build(true).unwrap();
+
+    fn check_sum(data: &mut VmData) -> VmResult {
+        assert_eq!(data.vstack, vec![Value::I32(6), Value::T(false)]);
+        Ok(())
+    }
+
+    run!(func, check_sum);
+}
+
+#[test]
+fn tuples_and_freeze() {
+    let func = func!({
+        ass().var("t").op(onewtuple().op(1).op("a").end()),
+        ass().var("d").op(onewdict().end()),
+        // an equal tuple is a valid key
+        push().var("d").op(oset().op(onewtuple().op(1).op("a").end()).op(5).end()),
+        pop().var("d"),
+        push().var("d").op(oget().var("t").end()),
+        push().var("t").op(oget().op(-1).end()),
+        push().op(cmp_eq().var("t").op(onewtuple().op(1).op("a").end()).end()),
+        push().op(cmp_eq().var("t").op(onewtuple().op(1).end()).end()),
+        ass().var("a").op(vec![1, 2]),
+        push().var("a").op(ofreeze().end()),
+        pop().var("a"),
+        push().var("a").op(oget().op(0).end()),
+        debug(),
+        push().var("a").op(oappend().op(3).end()),
+    });
+
+    fn check_values(data: &mut VmData) -> VmResult {
+        assert_eq!(
+            data.vstack,
+            vec![
+                Value::I32(5),
+                Value::from("a"),
+                Value::T(true),
+                Value::T(false),
+                Value::I32(1),
+            ]
+        );
+        Ok(())
+    }
+
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_values);
+    let msg = vm.run(&unit).unwrap_err();
+    assert_eq!(msg, "cannot modify frozen array");
+}
+
+#[derive(Clone, Debug)]
+struct Entity {
+    hp: i64,
+}
+
+impl ObjectProtocol for Entity {
+    fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
+        match key.to_string().as_ref() {
+            "damage" => Some(ObjectMethod::Native),
+            _ => None,
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &Name,
+        args: Vec<Value>,
+        _: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        expect_args(name, &args, 1..=1)?;
+        self.hp -= usize::try_from(args[0].clone())? as i64;
+        Ok(Some(Value::I64(self.hp)))
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+}
+
+#[test]
+fn host_types() {
+    let func = func!({
+        ass().var("e").op(onew("Entity").end()),
+        push().var("e"),
+        ocall("damage").op(30),
+        push().op(instance_of("Entity").var("e").end()),
+        push().op(instance_of("Entity").op(onewarray().end()).end()),
+        push().var("e"),
+        debug(),
+    });
+
+    fn check_entity(data: &mut VmData) -> VmResult {
+        let handle = match data.vstack.pop().unwrap() {
+            Value::Ref(handle) => handle,
+            _ => panic!("no object ref"),
+        };
+        assert_eq!(
+            data.vstack,
+            vec![Value::I64(70), Value::T(true), Value::T(false)]
+        );
+        data.obj_pool.get_as_mut::<Entity>(&handle)?.hp += 5;
+        assert_eq!(data.obj_pool.get_as::<Entity>(&handle)?.hp, 75);
+        assert!(data.obj_pool.get_as::<Array>(&handle).is_err());
+        Ok(())
+    }
+
+    let unit = UnitBuilder::from_object(func).build().unwrap();
+    let mut vm = vm::Vm::new();
+    vm.register_type("Entity", || Entity { hp: 100 }).unwrap();
+    assert!(vm.register_type("Entity", || Entity { hp: 0 }).is_err());
+    vm.interrupts_mut()
+        .set(vm::Interrupt::Debug as usize, &check_entity);
+    vm.run(&unit).expect("error in code");
+}
+
+#[test]
+fn finalizers() {
+    let resource = unit! {
+        __drop__ => func!([self] => {
+            push().var("log").op(oappend().op(push().var("self").op(oget().op("id").end()).end()).end()),
+        }),
+    };
+    let failing = unit! {
+        __drop__ => func!([self] => {
+            push().var("self").op(oget().op("missing").end()),
+        }),
+    };
+    let main = unit! {
+        main => func!({
+            ass().var("a").op(onew("Resource").end()),
+            push().var("a").op(oset().op("id").op(1).end()),
+            pop().var("a"),
+            ass().var("b").op(onew("Resource").end()),
+            push().var("b").op(oset().op("id").op(2).end()),
+            pop().var("b"),
+            // disposing is only a hint; nothing is released yet
+            push().var("a"),
+            odispose(),
+            ass().var("a").op(0),
+            ass().var("b").op(0),
+        }),
+    };
+    let failing_main = unit! {
+        main => func!({
+            ass().var("c").op(onew("Failing").end()),
+            ass().var("c").op(0),
+        }),
+    };
+
+    let mut vm = vm::Vm::new();
+    vm.data.units.load_ty(&resource, "Resource".into()).unwrap();
+    vm.data.units.load_ty(&failing, "Failing".into()).unwrap();
+    let log = vm.data.obj_pool.new_array_handle(AllocSite::default());
+    vm.data.globals.insert("log".into(), Value::Ref(log));
+    vm.run(&main).expect("error in code");
+    assert_eq!(vm.data.obj_pool.display(&Value::Ref(log)), "[]");
+    // finalizers run in reverse allocation order
+    assert_eq!(vm.collect_garbage(), Ok(0));
+    assert_eq!(vm.data.obj_pool.display(&Value::Ref(log)), "[2, 1]");
+
+    vm.run(&failing_main).expect("error in code");
+    let msg = vm.collect_garbage().unwrap_err();
+    assert!(msg.starts_with("finalizer of object"));
+    assert!(msg.ends_with("failed: object has no field `missing`"));
+    // the object is released anyway
+    assert_eq!(vm.heap_stats().live, 1);
+}
+
+#[test]
+fn dispose_loop() {
+    let mut co = CodeObject::new();
+    co.space.locals.push("keep".into());
+    co.space.locals.push("tmp".into());
+    co.space.locals.push("unused".into());
+    co.inner = vec![Code::ONewArray, Code::LPop(0)];
+    for _ in 0..4 * vm::VM_GC_THRESHOLD {
+        // every array stays reachable through `keep` but is disposed anyway
+        co.inner.extend(vec![
+            Code::LPush(0),
+            Code::ONewArray,
+            Code::Dup,
+            Code::LPop(1),
+            Code::OAppend,
+            Code::LPop(2),
+            Code::LPush(1),
+            Code::ODispose,
+        ]);
+    }
+    let unit = UnitBuilder::from_object(co).build().unwrap();
+
+    let mut vm = vm::Vm::new();
+    vm.run(&unit).expect("error in code");
+
+    // hints are collected in batches instead of once per `ODispose`
+    let stats = vm.heap_stats();
+    assert_eq!(stats.live, 4 * vm::VM_GC_THRESHOLD + 1);
+    assert!(stats.collections < 32);
+}
+
+#[derive(Clone, Debug)]
+struct File {
+    closed: Rc<RefCell<Vec<i64>>>,
+    fd: i64,
+}
+
+impl ObjectProtocol for File {
+    fn has_finalizer(&self) -> bool {
+        true
+    }
+
+    fn finalize(&mut self, _: &mut CallContext) -> Result<(), String> {
+        self.closed.borrow_mut().push(self.fd);
+        Ok(())
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+}
+
+#[test]
+fn native_finalizer() {
+    let closed = Rc::new(RefCell::new(vec![]));
+    let mut vm = vm::Vm::new();
+    for fd in 0..3 {
+        let file = File {
+            closed: closed.clone(),
+            fd,
+        };
+        vm.data
+            .obj_pool
+            .insert(Rc::new(RefCell::new(file)), AllocSite::default());
+    }
+    // finalizers run in reverse allocation order
+    assert_eq!(vm.collect_garbage(), Ok(0));
+    assert_eq!(*RefCell::borrow(&closed), vec![2, 1, 0]);
+    assert_eq!(vm.heap_stats().live, 0);
+}
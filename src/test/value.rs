@@ -0,0 +1,329 @@
+#![cfg(test)]
+use super::*;
+
+use std::str::FromStr;
+
+#[test]
+fn parse_suffixes() {
+    assert_eq!(Value::from_str("10").unwrap(), Value::I(10));
+    assert_eq!(Value::from_str("1000").unwrap(), Value::I64(1000));
+    assert_eq!(Value::from_str("10i16").unwrap(), Value::I16(10));
+    assert_eq!(Value::from_str("-10i32").unwrap(), Value::I32(-10));
+    assert_eq!(Value::from_str("10u8").unwrap(), Value::U8(10));
+    assert_eq!(Value::from_str("10u16").unwrap(), Value::U16(10));
+    assert_eq!(Value::from_str("10u32").unwrap(), Value::U32(10));
+    assert_eq!(Value::from_str("10u64").unwrap(), Value::U64(10));
+    assert_eq!(Value::from_str("1.5f32").unwrap(), Value::F32(1.5));
+    assert_eq!(Value::from_str("2f64").unwrap(), Value::F64(2.));
+    assert!(Value::from_str("256u8").is_err());
+    assert!(Value::from_str("-1u16").is_err());
+}
+
+#[test]
+fn cast_family() {
+    let n = Value::I16(-1);
+    assert_eq!(n.cast(&Value::I32(0)), Value::I32(-1));
+    assert_eq!(n.cast(&Value::U8(0)), Value::U8(255));
+    assert_eq!(Value::U64(300).cast(&Value::U8(0)), Value::U8(44));
+    assert_eq!(Value::F32(2.5).cast(&Value::F64(0.)), Value::F64(2.5));
+    assert_eq!(Value::C('a').cast(&Value::U32(0)), Value::U32(97));
+    assert_eq!(Value::U16(1).cast(&Value::T(false)), Value::T(true));
+    assert!(Value::from("a").try_cast(&Value::U16(0)).is_err());
+}
+
+#[test]
+fn arithmetic_family() {
+    assert_eq!(Value::U16(1000).add(&Value::I(24)), Ok(Value::U16(1024)));
+    assert_eq!(Value::I32(7).rem(&Value::I32(4)), Ok(Value::I32(3)));
+    assert_eq!(Value::U8(2).pow(&Value::U8(7)), Ok(Value::U8(128)));
+    assert_eq!(Value::F32(1.5).mul(&Value::I(2)), Ok(Value::F32(3.)));
+    assert_eq!(Value::U32(1).shl(&Value::U32(4)), Ok(Value::U32(16)));
+    assert!(Value::U64(2) < Value::U64(3));
+}
+
+#[test]
+fn overflow_modes() {
+    use crate::vm::BinaryOp::*;
+    let max = Value::I(127);
+    let one = Value::I(1);
+
+    let apply = |op, lhs: &Value, rhs: &Value, mode| lhs.arith(op, rhs, mode);
+    assert_eq!(
+        apply(Add, &max, &one, ArithmeticMode::Wrapping),
+        Ok(Value::I(-128))
+    );
+    assert!(apply(Add, &max, &one, ArithmeticMode::Checked).is_err());
+    assert_eq!(
+        apply(Add, &max, &one, ArithmeticMode::Saturating),
+        Ok(Value::I(127))
+    );
+    assert_eq!(
+        apply(Add, &max, &one, ArithmeticMode::Promote),
+        Ok(Value::I64(128))
+    );
+
+    let min = Value::I(-128);
+    assert_eq!(
+        apply(Mul, &min, &Value::I(2), ArithmeticMode::Saturating),
+        Ok(Value::I(-128))
+    );
+    assert_eq!(
+        apply(
+            Sub,
+            &Value::U8(0),
+            &Value::U8(1),
+            ArithmeticMode::Saturating
+        ),
+        Ok(Value::U8(0))
+    );
+    assert_eq!(
+        apply(Div, &min, &Value::I(-1), ArithmeticMode::Wrapping),
+        Ok(Value::I(-128))
+    );
+    assert_eq!(min.negate(ArithmeticMode::Promote), Ok(Value::I64(128)));
+    assert!(min.negate(ArithmeticMode::Checked).is_err());
+
+    // negative exponents build the reciprocal
+    assert_eq!(
+        apply(Pow, &Value::I(2), &Value::I(-1), ArithmeticMode::Checked),
+        Ok(Value::I(0))
+    );
+    assert_eq!(
+        apply(Pow, &Value::I(-1), &Value::I(-3), ArithmeticMode::Checked),
+        Ok(Value::I(-1))
+    );
+    assert_eq!(
+        apply(Pow, &Value::I(2), &Value::I(10), ArithmeticMode::Promote),
+        Ok(Value::I64(1024))
+    );
+
+    for mode in vec![
+        ArithmeticMode::Wrapping,
+        ArithmeticMode::Checked,
+        ArithmeticMode::Saturating,
+        ArithmeticMode::Promote,
+    ] {
+        assert!(apply(Div, &one, &Value::I(0), mode).is_err());
+        assert!(apply(Rem, &one, &Value::I(0), mode).is_err());
+        assert!(apply(Pow, &Value::I(0), &Value::I(-1), mode).is_err());
+    }
+}
+
+#[test]
+fn overflow_modes_mixed_width() {
+    use crate::vm::BinaryOp::*;
+    let apply = |lhs: &Value, rhs: &Value, mode| lhs.arith(Add, rhs, mode);
+    let (small, wide) = (Value::I(127), Value::I64(128));
+    // the right operand is wider than the left one and must not be truncated
+    let (low, high) = (Value::I32(1), Value::I64(1 << 32));
+
+    assert_eq!(
+        apply(&small, &wide, ArithmeticMode::Wrapping),
+        Ok(Value::I(-1))
+    );
+    assert!(apply(&small, &wide, ArithmeticMode::Checked).is_err());
+    assert!(apply(&low, &high, ArithmeticMode::Checked).is_err());
+    assert_eq!(
+        apply(&small, &wide, ArithmeticMode::Saturating),
+        Ok(Value::I(127))
+    );
+    assert_eq!(
+        apply(&low, &high, ArithmeticMode::Saturating),
+        Ok(Value::I32(i32::MAX))
+    );
+    assert_eq!(
+        apply(&small, &wide, ArithmeticMode::Promote),
+        Ok(Value::I64(255))
+    );
+    assert_eq!(
+        apply(&low, &high, ArithmeticMode::Promote),
+        Ok(Value::I64((1 << 32) + 1))
+    );
+    // the result fits even though the right operand does not
+    assert_eq!(
+        apply(&Value::U8(200), &Value::I64(-150), ArithmeticMode::Checked),
+        Ok(Value::U8(50))
+    );
+}
+
+#[test]
+fn serialize_family() {
+    let mut co = CodeObject::new();
+    co.space.consts = vec![
+        Value::I16(-16),
+        Value::I32(-32),
+        Value::U8(8),
+        Value::U16(16),
+        Value::U32(32),
+        Value::U64(64),
+        Value::F32(0.5),
+    ];
+    let mut unit = Unit::new();
+    unit.set(&"main".to_string(), co);
+
+    let bytes = unit.serialize().expect("serialize failed");
+    let restored = Unit::deserialize(bytes.as_ref()).expect("deserialize failed");
+    assert_eq!(unit, restored);
+}
+
+#[test]
+fn serialize_layout() {
+    // discriminants of values written before the numeric family was added
+    let tag = |value: Value| bincode::serialize(&value).unwrap()[0];
+    assert_eq!(tag(Value::I(1)), 0);
+    assert_eq!(tag(Value::I64(1)), 1);
+    assert_eq!(tag(Value::F64(1.)), 2);
+    assert_eq!(tag(Value::Ref(1)), 3);
+    assert_eq!(tag(Value::T(true)), 4);
+    assert_eq!(tag(Value::C('a')), 5);
+    assert_eq!(tag(Value::from("a")), 6);
+}
+
+#[test]
+fn serialize_opcodes() {
+    // opcodes of instructions that existed before the instruction set was extended
+    let tag = |code: Code| bincode::serialize(&code).unwrap()[0];
+    assert_eq!(tag(Code::Dup), 0);
+    assert_eq!(tag(Code::CmpEq), 15);
+    assert_eq!(tag(Code::CPush(0)), 24);
+    assert_eq!(tag(Code::Cast(0)), 31);
+    assert_eq!(tag(Code::ONew(0)), 36);
+    assert_eq!(tag(Code::ODispose), 39);
+    assert_eq!(tag(Code::OAppend), 43);
+}
+
+#[test]
+fn nil_semantics() {
+    assert_eq!(Value::Nil, Value::Nil);
+    assert_ne!(Value::Nil, Value::I(0));
+    assert_ne!(Value::Nil, Value::T(false));
+    assert_eq!(Value::Nil.cast(&Value::T(true)), Value::T(false));
+    assert!(Value::Nil.try_cast(&Value::I64(0)).is_err());
+    assert!(Value::I64(0).try_cast(&Value::Nil).is_err());
+    assert!(!bool::from(Value::Nil));
+    assert_eq!(Value::Nil.to_string(), "nil");
+    assert_eq!(Value::from_str("nil").unwrap(), Value::Nil);
+    assert!(Value::Nil.loose_cmp(&Value::I(1)).is_none());
+}
+
+#[test]
+fn bigint_matches_i128() {
+    let samples: Vec<i128> = vec![
+        0,
+        1,
+        -1,
+        7,
+        -13,
+        4_294_967_295,
+        4_294_967_296,
+        -4_294_967_297,
+        i64::max_value() as i128,
+        i64::min_value() as i128,
+        123_456_789_012_345_678_901,
+        -98_765_432_109_876_543_210,
+    ];
+    for l in samples.iter() {
+        let lhs = BigInt::from(*l);
+        assert_eq!(lhs.to_string(), l.to_string());
+        assert_eq!(BigInt::from_str(&l.to_string()).unwrap(), lhs);
+        for r in samples.iter() {
+            let rhs = BigInt::from(*r);
+            assert_eq!(lhs.add(&rhs).to_i128(), Some(l + r));
+            assert_eq!(lhs.sub(&rhs).to_i128(), Some(l - r));
+            assert_eq!(lhs.and(&rhs).to_i128(), Some(l & r));
+            assert_eq!(lhs.or(&rhs).to_i128(), Some(l | r));
+            assert_eq!(lhs.xor(&rhs).to_i128(), Some(l ^ r));
+            assert_eq!(lhs.cmp(&rhs), l.cmp(r));
+            if let Some(prod) = l.checked_mul(*r) {
+                assert_eq!(lhs.mul(&rhs).to_i128(), Some(prod));
+            }
+            if *r != 0 {
+                let (quot, rem) = lhs.div_rem(&rhs).unwrap();
+                assert_eq!((quot.to_i128(), rem.to_i128()), (Some(l / r), Some(l % r)));
+            }
+        }
+        for n in vec![0, 1, 5, 31, 32, 33, 64] {
+            assert_eq!(lhs.shr(n).to_i128(), Some(l >> n));
+        }
+    }
+}
+
+#[test]
+fn bigint_values() {
+    let big = Value::from_str("170141183460469231731687303715884105728").unwrap();
+    assert_eq!(
+        big,
+        Value::Big(BigInt::from(i128::max_value()).add(&BigInt::one()))
+    );
+    assert_eq!(
+        big.sub(&Value::I(1)).unwrap().to_string(),
+        i128::max_value().to_string()
+    );
+    assert_eq!(
+        Value::I(2)
+            .pow(&Value::Big(BigInt::from(100i64)))
+            .unwrap()
+            .to_string(),
+        "1267650600228229401496703205376"
+    );
+    assert!(big.div(&Value::Big(BigInt::zero())).is_err());
+    // the result of huge powers is rejected before it is computed
+    let huge = Value::U32(4_000_000_000);
+    let promote =
+        |lhs: Value, rhs: &Value| lhs.arith(vm::BinaryOp::Pow, rhs, ArithmeticMode::Promote);
+    assert!(Value::I(2).pow(&huge).is_err());
+    assert_eq!(
+        promote(Value::I(2), &huge),
+        Err("exponent `4000000000` too large".to_string())
+    );
+    assert!(big.pow(&huge).is_err());
+    assert_eq!(promote(Value::I64(-1), &huge), Ok(Value::I64(1)));
+    assert_eq!(
+        Value::Big(BigInt::one()).pow(&huge),
+        Ok(Value::Big(BigInt::one()))
+    );
+    assert!(promote(Value::I(2), &Value::I32(1 << 16)).is_ok());
+    assert!(promote(Value::I(2), &Value::I32((1 << 16) + 1)).is_err());
+    assert!(Value::I64(1) < big);
+    assert!(big > Value::I64(1));
+    assert_eq!(
+        Value::Big(BigInt::from(300i64)).cast(&Value::U8(0)),
+        Value::U8(44)
+    );
+    assert_eq!(
+        Value::F64(1e20).cast(&big).to_string(),
+        "100000000000000000000"
+    );
+
+    let max = Value::I64(i64::max_value());
+    assert_eq!(
+        max.arith(vm::BinaryOp::Add, &Value::I64(1), ArithmeticMode::Promote),
+        Ok(Value::Big(BigInt::from(i64::max_value() as i128 + 1)))
+    );
+
+    let mut dict = vm::object::Dict::new();
+    dict.inner_mut().insert(big.clone(), Value::T(true));
+    assert!(dict.inner().contains_key(&big.mul(&Value::I(1)).unwrap()));
+
+    let mut co = CodeObject::new();
+    co.space.consts = vec![big.clone(), big.neg().unwrap()];
+    let mut unit = Unit::new();
+    unit.set(&"main".to_string(), co);
+    let restored = Unit::deserialize(unit.serialize().unwrap().as_ref()).unwrap();
+    assert_eq!(unit, restored);
+}
+
+#[test]
+fn value_types() {
+    for (idx, ty) in ValueType::ALL.iter().enumerate() {
+        assert_eq!(*ty as usize, idx);
+        assert_eq!(ValueType::from_index(idx), Some(*ty));
+        assert_eq!(ValueType::from_str(ty.name()), Ok(*ty));
+        assert_eq!(Value::from_type(*ty).ty(), *ty);
+    }
+    assert_eq!(ValueType::from_index(ValueType::ALL.len()), None);
+    assert!(Value::from("a").cast_to(ValueType::I64).is_err());
+    assert!(Value::Ref(1).cast_to(ValueType::U64).is_err());
+    assert!(Value::U64(1).cast_to(ValueType::Ref).is_err());
+    assert_eq!(Value::from(3usize), Value::U64(3));
+}
@@ -9,7 +9,7 @@ pub struct VmFrame {
 impl VmFrame {
     pub fn new(argc: usize) -> Self {
         Self {
-            locals: (0..argc).map(|_| Value::I(0)).collect(),
+            locals: (0..argc).map(|_| Value::Nil).collect(),
         }
     }
 }
@@ -5,10 +5,13 @@ use super::*;
 pub enum Interrupt {
     Debug = 10,
     Put = 20,
+    // like `Put` but strings are quoted
+    PutRepr = 21,
 }
 
 pub type InterruptHandler = &'static dyn Fn(&mut VmData) -> VmResult;
 
+#[derive(Clone)]
 pub struct Interrupts([Option<InterruptHandler>; 256]);
 
 impl Interrupts {
@@ -35,13 +38,20 @@ impl std::default::Default for Interrupts {
         let mut ints = Interrupts::new();
         ints.set(Interrupt::Debug as usize, &debug);
         ints.set(Interrupt::Put as usize, &put);
+        ints.set(Interrupt::PutRepr as usize, &put_repr);
         ints
     }
 }
 
 fn put(data: &mut VmData) -> VmResult {
     let v = data.vstack.last().expect("no operand");
-    print!("{}", v.to_string());
+    print!("{}", data.obj_pool.display(v));
+    Ok(())
+}
+
+fn put_repr(data: &mut VmData) -> VmResult {
+    let v = data.vstack.last().expect("no operand");
+    print!("{}", data.obj_pool.repr(v));
     Ok(())
 }
 
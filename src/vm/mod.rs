@@ -9,14 +9,19 @@ use super::*;
 pub use self::frame::*;
 pub use self::interrupt::*;
 pub use self::object::*;
+pub use self::operation::*;
 pub use self::unit::*;
 
 pub use std::collections::HashMap;
 
+use std::convert::TryFrom;
+
 // the vm is meant to be used as a dynamic runtime. it keeps track of:
 //  - globals: area for storing global vm values
+//  - arithmetic: overflow behavior if the running `CodeObject` does not define one
 //  - units: loaded vm units; used for name lookup (e.g. in function call)
-//  - obj_pool: all allocated custom objects
+//  - host_types: object types implemented by the embedding application
+//  - obj_pool: all allocated custom objects; released by a tracing collector
 //  - state: status flag for vm flow control
 //  - stack: callstack consisting of local frames
 //  - vstack: global value stack; used for returning values (?)
@@ -46,7 +51,9 @@ pub enum VmState {
 #[derive(Clone, Debug)]
 pub struct VmData {
     pub globals: HashMap<Name, Value>,
+    pub arithmetic: ArithmeticMode,
     pub units: Units,
+    pub host_types: HostTypes,
     pub obj_pool: ObjectPool,
     pub state: VmState,
     pub stack: Vec<VmFrame>,
@@ -57,17 +64,42 @@ impl VmData {
     pub fn new() -> Self {
         Self {
             globals: HashMap::new(),
+            arithmetic: ArithmeticMode::default(),
             units: Units::new(),
+            host_types: HostTypes::new(),
             obj_pool: ObjectPool::new(),
             state: VmState::Initial,
             stack: vec![],
             vstack: vec![],
         }
     }
+
+    // runs a full collection cycle. roots are the value stack, the locals of
+    // every frame and all globals. returns the amount of freed objects.
+    pub fn collect_garbage(&mut self) -> usize {
+        let roots = self
+            .vstack
+            .iter()
+            .chain(self.stack.iter().flat_map(|frame| frame.locals.iter()))
+            .chain(self.globals.values());
+        self.obj_pool.collect(roots)
+    }
 }
 
+// a running function. for methods, `owner` is the type defining it.
+#[derive(Clone, Debug)]
+struct Call {
+    name: Name,
+    owner: Option<Name>,
+}
+
+#[derive(Clone)]
 pub struct Vm {
     interrupts: Interrupts,
+    // functions currently being executed; used for allocation sites and `OSuper`
+    calls: Vec<Call>,
+    // set while finalizers are running
+    finalizing: bool,
     pub data: VmData,
 }
 
@@ -75,6 +107,8 @@ impl Vm {
     pub fn new() -> Self {
         Self {
             interrupts: Interrupts::default(),
+            calls: vec![],
+            finalizing: false,
             data: VmData::new(),
         }
     }
@@ -82,6 +116,35 @@ impl Vm {
     pub fn interrupts_mut(&mut self) -> &mut Interrupts {
         &mut self.interrupts
     }
+
+    // runs a full collection including the finalizers of released objects.
+    // returns the amount of objects released without a finalizer.
+    pub fn collect_garbage(&mut self) -> Result<usize, String> {
+        let freed = self.data.collect_garbage();
+        // finalizers are run like methods, so the vm has to be running
+        let state = std::mem::replace(&mut self.data.state, VmState::Running);
+        self.push_frame(0);
+        let result = self.run_finalizers(0);
+        self.data.stack.pop();
+        if result.is_ok() {
+            self.data.state = state;
+        }
+        result.map(|_| freed)
+    }
+
+    pub fn heap_stats(&self) -> HeapStats {
+        self.data.obj_pool.stats()
+    }
+
+    // makes `ONew(name)` create objects via `factory`. type units loaded
+    // under the same name take precedence.
+    pub fn register_type<T, F>(&mut self, name: &str, factory: F) -> Result<(), String>
+    where
+        T: ObjectProtocol + 'static,
+        F: Fn() -> T + 'static,
+    {
+        self.data.host_types.register(name, factory)
+    }
 }
 
 impl Vm {
@@ -136,14 +199,43 @@ impl Vm {
                     let dup = self.data.vstack.last().expect("no value").clone();
                     self.data.vstack.push(dup);
                 }
+                Code::Swap => {
+                    let len = self.data.vstack.len();
+                    if len < 2 {
+                        self.panic("not enough values to swap")?;
+                    }
+                    self.data.vstack.swap(len - 2, len - 1);
+                }
+                Code::Pop => {
+                    self.data.vstack.pop().expect("no value");
+                }
                 Code::Int(idx) => {
                     if let Some(irh) = self.interrupts.get(*idx) {
                         irh(&mut self.data)?;
+                        // the handler could have triggered a collection
+                        self.run_finalizers(ip)?;
                     }
                 }
-                Code::Cast(ty_idx) => {
+                Code::Cast(ty_idx) | Code::IsType(ty_idx) | Code::AssertType(ty_idx) => {
+                    let ty = match ValueType::from_index(*ty_idx) {
+                        Some(ty) => ty,
+                        _ => self.panic(format!("type index `{}` not defined", ty_idx))?,
+                    };
+                    let val = self.data.vstack.last().expect("no value");
+                    let result = match inx {
+                        Code::Cast(_) => val.cast_to(ty),
+                        Code::IsType(_) => Ok(Value::T(val.ty() == ty)),
+                        Code::AssertType(_) if val.ty() == ty => Ok(val.clone()),
+                        _ => Err(format!("expected `{}` but got `{}`", ty, val.ty())),
+                    };
+                    match result {
+                        Ok(result) => *self.data.vstack.last_mut().unwrap() = result,
+                        Err(msg) => self.panic(msg)?,
+                    }
+                }
+                Code::TypeOf => {
                     let val = self.data.vstack.last_mut().expect("no value");
-                    *val = val.cast(&Value::from_type(*ty_idx));
+                    *val = Value::from(val.ty().name());
                 }
                 Code::LPop(idx) | Code::GPop(idx) => {
                     let value = self.data.vstack.pop().expect("no value");
@@ -179,7 +271,22 @@ impl Vm {
                 Code::GCall(idx) => {
                     let fname = &co.space.globals[*idx];
                     let co = self.call_lookup(&fname.to_string())?;
-                    self.run_object(co)?;
+                    // arguments are consumed by the callee; everything above is its return value
+                    let argc = {
+                        let callee: &CodeObject = co.borrow();
+                        callee.argc
+                    };
+                    let stack_size_after = self.data.vstack.len().saturating_sub(argc);
+                    self.calls.push(Call {
+                        name: fname.clone(),
+                        owner: None,
+                    });
+                    let result = self.run_object(co);
+                    self.calls.pop();
+                    result?;
+                    if self.data.vstack.len() == stack_size_after {
+                        self.data.vstack.push(Value::Nil);
+                    }
                 }
                 Code::Inc | Code::Dec => {
                     unimplemented!();
@@ -193,9 +300,22 @@ impl Vm {
                     //    _ => unreachable!(),
                     //}
                 }
+                Code::Neg if is_ref(self.data.vstack.last().expect("no target")) => {
+                    let target = self.data.vstack.pop().unwrap();
+                    match self.call_method(ip, "__neg__", &target, vec![])? {
+                        Some(result) => self.data.vstack.push(result),
+                        _ => {
+                            let msg = undefined_operator(&self.data, &[("__neg__", &target)]);
+                            self.panic(msg)?
+                        }
+                    }
+                }
                 Code::Neg => {
-                    let target = self.data.vstack.last_mut().expect("no target");
-                    *target = target.neg();
+                    let target = self.data.vstack.last().expect("no target");
+                    match target.negate(co.arithmetic.unwrap_or(self.data.arithmetic)) {
+                        Ok(result) => *self.data.vstack.last_mut().unwrap() = result,
+                        Err(msg) => self.panic(msg)?,
+                    }
                 }
                 Code::Add
                 | Code::Sub
@@ -209,26 +329,25 @@ impl Vm {
                 | Code::Shl
                 | Code::Shr => {
                     let op = self.data.vstack.pop().expect("no operand");
-                    let target = self.data.vstack.last_mut().expect("no target");
+                    let binop = BinaryOp::from_code(inx).unwrap();
+                    if is_ref(&op) || is_ref(self.data.vstack.last().expect("no target")) {
+                        let target = self.data.vstack.pop().unwrap();
+                        let methods = (binop.method(), binop.reflected_method());
+                        let result = self.call_operator(ip, methods, target, op)?;
+                        self.data.vstack.push(result);
+                    } else {
+                        let target = self.data.vstack.last_mut().unwrap();
+
+                        if cfg!(debug_assertions) {
+                            println!("target({:?}) {:?} {:?}", target, inx, op);
+                        }
 
-                    if cfg!(debug_assertions) {
-                        println!("target({:?}) {:?} {:?}", target, inx, op);
+                        let mode = co.arithmetic.unwrap_or(self.data.arithmetic);
+                        match target.arith(binop, &op, mode) {
+                            Ok(result) => *target = result,
+                            Err(msg) => self.panic(msg)?,
+                        }
                     }
-
-                    *target = match inx {
-                        Code::Add => target.add(&op),
-                        Code::Sub => target.sub(&op),
-                        Code::Mul => target.mul(&op),
-                        Code::Div => target.div(&op),
-                        Code::Rem => target.rem(&op),
-                        Code::Pow => target.pow(&op),
-                        Code::And => target.and(&op),
-                        Code::Or => target.or(&op),
-                        Code::Xor => target.xor(&op),
-                        Code::Shl => target.shl(&op),
-                        Code::Shr => target.shr(&op),
-                        _ => unimplemented!(),
-                    };
                 }
                 Code::CmpEq
                 | Code::CmpNe
@@ -240,15 +359,21 @@ impl Vm {
                     let op1 = self.data.vstack.pop().expect("missing op1");
                     let op2 = self.data.vstack.pop().expect("missing op2");
                     let inx = *inx;
-                    let cond = match op2.partial_cmp(&op1).unwrap() {
-                        Ordering::Equal => {
-                            inx == Code::CmpEq || inx == Code::CmpGe || inx == Code::CmpLe
-                        }
-                        Ordering::Greater => {
-                            inx == Code::CmpNe || inx == Code::CmpGe || inx == Code::CmpGt
-                        }
-                        Ordering::Less => {
-                            inx == Code::CmpNe || inx == Code::CmpLe || inx == Code::CmpLt
+                    let cond = if is_ref(&op1) || is_ref(&op2) {
+                        self.compare_objects(ip, inx, op2, op1)?
+                    } else {
+                        match op2.loose_cmp(&op1) {
+                            // incomparable values (e.g. nil and a number) are only unequal
+                            None => inx == Code::CmpNe,
+                            Some(Ordering::Equal) => {
+                                inx == Code::CmpEq || inx == Code::CmpGe || inx == Code::CmpLe
+                            }
+                            Some(Ordering::Greater) => {
+                                inx == Code::CmpNe || inx == Code::CmpGe || inx == Code::CmpGt
+                            }
+                            Some(Ordering::Less) => {
+                                inx == Code::CmpNe || inx == Code::CmpLe || inx == Code::CmpLt
+                            }
                         }
                     };
                     self.data.vstack.push(Value::T(cond));
@@ -269,76 +394,239 @@ impl Vm {
                     }
                 }
                 Code::ONew(idx) => {
+                    self.collect_if_needed(ip)?;
                     let ty = &co.space.globals[*idx];
-                    let uref = self.data.units.lookup_ty(ty).expect("unknown type");
-                    let handle = self.data.obj_pool.new_handle_with_assoc(uref);
+                    let site = self.site(ip);
+                    let handle = match self.data.units.type_chain(ty) {
+                        Some(chain) => self.data.obj_pool.new_handle_with_type(chain, site),
+                        _ => match self.data.host_types.create(ty) {
+                            Some(object) => self.data.obj_pool.insert(object, site),
+                            _ => self.panic(format!("type `{}` is unknown", ty))?,
+                        },
+                    };
                     self.data.vstack.push(Value::Ref(handle));
                 }
                 Code::ONewDict => {
-                    let handle = self.data.obj_pool.new_dict_handle();
+                    self.collect_if_needed(ip)?;
+                    let handle = self.data.obj_pool.new_dict_handle(self.site(ip));
+                    self.data.vstack.push(Value::Ref(handle));
+                }
+                Code::ONewSet => {
+                    self.collect_if_needed(ip)?;
+                    let handle = self.data.obj_pool.new_set_handle(self.site(ip));
                     self.data.vstack.push(Value::Ref(handle));
                 }
+                Code::ONewTuple(n) => {
+                    self.collect_if_needed(ip)?;
+                    let base = match self.data.vstack.len().checked_sub(*n) {
+                        Some(base) => base,
+                        _ => self.panic(format!("not enough values for tuple of length {}", n))?,
+                    };
+                    let items = self.data.vstack.drain(base..).collect::<Vec<_>>();
+                    let site = self.site(ip);
+                    let handle = self.data.obj_pool.new_tuple_handle(items, site);
+                    self.data.vstack.push(Value::Ref(handle));
+                }
+                Code::OFreeze => {
+                    let object = match object_ref(&self.data) {
+                        Ok(object) => object,
+                        Err(msg) => self.panic(msg)?,
+                    };
+                    let result = object.borrow_mut().freeze();
+                    if let Err(msg) = result {
+                        self.panic(msg)?;
+                    }
+                }
                 Code::ONewArray => {
-                    let handle = self.data.obj_pool.new_array_handle();
+                    self.collect_if_needed(ip)?;
+                    let handle = self.data.obj_pool.new_array_handle(self.site(ip));
                     self.data.vstack.push(Value::Ref(handle));
                 }
                 Code::ODispose => {
-                    let handle = usize::from(self.data.vstack.pop().expect("no object"));
-                    self.data.obj_pool.dispose_handle(&handle);
+                    let handle = match self.data.vstack.pop().expect("no object") {
+                        Value::Ref(handle) => handle,
+                        other => {
+                            self.panic(format!("cannot dispose `{}`; not an object", other))?
+                        }
+                    };
+                    // disposing is only a hint: the object is released by the next
+                    // collection if it is not reachable anymore.
+                    if let Err(msg) = self.data.obj_pool.resolve(&handle) {
+                        self.panic(msg)?;
+                    }
+                    self.data.obj_pool.hint_dispose(&handle);
+                    self.collect_if_needed(ip)?;
                 }
-                Code::OCall(idx) => {
+                Code::OCall(idx) | Code::OSuper(idx) => {
                     let name = &co.space.consts[*idx];
-                    let argc = self.data.vstack.pop().expect("no argc");
-                    let stack_size_after = self.data.vstack.len() - usize::from(argc);
-                    let params = self
-                        .data
-                        .vstack
-                        .drain(stack_size_after..)
-                        .collect::<Vec<_>>();
-                    println!("calling {:?} with {:?}", name, params);
-                    let mut object = object_mut(&mut self.data);
-                    match object.lookup(&name) {
-                        Some(ObjectMethod::Virtual(cb)) => {
-                            drop(object);
-                            self.run_object(cb)?;
+                    let argc = match usize::try_from(self.data.vstack.pop().expect("no argc")) {
+                        Ok(argc) => argc,
+                        Err(msg) => self.panic(format!("invalid argument count: {}", msg))?,
+                    };
+                    // the receiver is located right below the arguments
+                    let len = self.data.vstack.len();
+                    let base = match argc.checked_add(1).and_then(|n| len.checked_sub(n)) {
+                        Some(base) => base,
+                        _ => self.panic(format!("not enough arguments for method `{}`", name))?,
+                    };
+                    let object = match resolve(&self.data, &self.data.vstack[base]) {
+                        Ok(object) => object,
+                        Err(msg) => self.panic(msg)?,
+                    };
+                    // `super` starts the lookup above the type defining the running method
+                    let after = match inx {
+                        Code::OSuper(_) => match self.calls.last().and_then(|c| c.owner.clone()) {
+                            Some(owner) => Some(owner),
+                            _ => self.panic("`super` can only be called inside a method")?,
+                        },
+                        _ => None,
+                    };
+                    match find_method(&object, name, after.as_ref()) {
+                        // receiver and arguments stay on the stack and are popped
+                        // into the locals `self, arg1, ...` by the callee
+                        Some((owner, ObjectMethod::Virtual(cb))) => {
+                            self.call_virtual(&name.to_string(), owner, cb, argc + 1)?;
                         }
                         // TODO: this should only allow strings
-                        Some(ObjectMethod::Native) => match object.call(&name.to_string()) {
-                            Ok(Some(val)) => {
-                                drop(object);
-                                self.data.vstack.push(val);
+                        Some((_, ObjectMethod::Native)) => {
+                            let args = self.data.vstack.drain(base + 1..).collect::<Vec<_>>();
+                            self.data.vstack.pop();
+                            let mut ctx = CallContext {
+                                site: self.site(ip),
+                                data: &mut self.data,
+                            };
+                            match object.borrow_mut().call(&name.to_string(), args, &mut ctx) {
+                                Ok(Some(val)) => self.data.vstack.push(val),
+                                Ok(_) => {}
+                                Err(msg) => self.panic(msg)?,
                             }
-                            Ok(_) => {}
-                            _ => panic!("native call error"),
-                        },
-                        _ => panic!("method `{:?}` not found", name),
+                        }
+                        _ => self.panic(format!("method `{}` not found", name))?,
+                    }
+                    // like functions, methods without return value evaluate to nil
+                    if self.data.vstack.len() == base {
+                        self.data.vstack.push(Value::Nil);
                     }
                 }
-                Code::OAppend => {
+                Code::OInstanceOf(idx) => {
+                    let ty = &co.space.globals[*idx];
                     let value = self.data.vstack.pop().expect("no value");
-                    object_mut(&mut self.data)
-                        .as_indexable()
-                        .unwrap()
-                        .append(value);
-                }
-                Code::OGet(idx) => {
-                    let aname = &co.space.consts[*idx];
-                    let value = {
-                        let mut object = object_mut(&mut self.data);
-                        object
-                            .as_indexable()
-                            .unwrap()
-                            .getk(&aname)
-                            .expect("unknown attribute")
-                            .clone()
+                    let is_instance = match &value {
+                        Value::Ref(_) => {
+                            let object = match resolve(&self.data, &value) {
+                                Ok(object) => object,
+                                Err(msg) => self.panic(msg)?,
+                            };
+                            let object = object.borrow_mut();
+                            match object.as_any().downcast_ref::<Object>() {
+                                Some(object) => object.is_instance_of(ty),
+                                _ => self.data.host_types.is_instance_of(&*object, ty),
+                            }
+                        }
+                        _ => false,
                     };
-                    self.data.vstack.push(value);
+                    self.data.vstack.push(Value::T(is_instance));
                 }
-                Code::OSet(idx) => {
-                    let value = self.data.vstack.pop().expect("no value");
-                    let mut object = object_mut(&mut self.data);
-                    let aname = &co.space.consts[*idx];
-                    object.as_indexable().unwrap().setk(&aname, value);
+                Code::OIter => {
+                    let object = match object_ref(&self.data) {
+                        Ok(object) => object,
+                        Err(msg) => self.panic(msg)?,
+                    };
+                    let base = self.data.vstack.len() - 1;
+                    match find_method(&object, &Value::from("__iter__"), None) {
+                        Some((owner, ObjectMethod::Virtual(cb))) => {
+                            self.call_virtual("__iter__", owner, cb, 1)?;
+                            if self.data.vstack.len() == base {
+                                self.panic("`__iter__` did not return an iterator")?;
+                            }
+                        }
+                        _ => {
+                            self.data.vstack.pop();
+                            let mut ctx = CallContext {
+                                site: self.site(ip),
+                                data: &mut self.data,
+                            };
+                            match object.borrow_mut().iter(&mut ctx) {
+                                Ok(iter) => self.data.vstack.push(iter),
+                                Err(msg) => self.panic(msg)?,
+                            }
+                        }
+                    }
+                }
+                Code::ONext(nip) => {
+                    let object = match object_ref(&self.data) {
+                        Ok(object) => object,
+                        Err(msg) => self.panic(msg)?,
+                    };
+                    let base = self.data.vstack.len();
+                    // virtual iterators signal exhaustion by not returning a value
+                    let item = match find_method(&object, &Value::from("__next__"), None) {
+                        Some((owner, ObjectMethod::Virtual(cb))) => {
+                            let iter = self.data.vstack.last().unwrap().clone();
+                            self.data.vstack.push(iter);
+                            self.call_virtual("__next__", owner, cb, 1)?;
+                            match self.data.vstack.len() {
+                                len if len == base => None,
+                                _ => self.data.vstack.pop(),
+                            }
+                        }
+                        _ => {
+                            let mut ctx = CallContext {
+                                site: self.site(ip),
+                                data: &mut self.data,
+                            };
+                            match object.borrow_mut().next(&mut ctx) {
+                                Ok(item) => item,
+                                Err(msg) => self.panic(msg)?,
+                            }
+                        }
+                    };
+                    match item {
+                        Some(item) => self.data.vstack.push(item),
+                        _ => {
+                            self.data.vstack.pop();
+                            ip = *nip;
+                            continue;
+                        }
+                    }
+                }
+                Code::OAppend | Code::OGet(_) | Code::OSet(_) | Code::OGetDyn | Code::OSetDyn => {
+                    let value = match inx {
+                        Code::OAppend | Code::OSet(_) | Code::OSetDyn => {
+                            Some(self.data.vstack.pop().expect("no value"))
+                        }
+                        _ => None,
+                    };
+                    // keys are either constants or taken from the stack
+                    let dyn_key;
+                    let key = match inx {
+                        Code::OGet(idx) | Code::OSet(idx) => Some(&co.space.consts[*idx]),
+                        Code::OGetDyn | Code::OSetDyn => {
+                            dyn_key = self.data.vstack.pop().expect("no key");
+                            Some(&dyn_key)
+                        }
+                        _ => None,
+                    };
+                    let object = match object_ref(&self.data) {
+                        Ok(object) => object,
+                        Err(msg) => self.panic(msg)?,
+                    };
+                    let mut object = object.borrow_mut();
+                    let object = match object.as_indexable() {
+                        Ok(object) => object,
+                        Err(_) => self.panic("object is not indexable")?,
+                    };
+                    let result = match (key, value) {
+                        (None, Some(value)) => object.append(value),
+                        (Some(key), Some(value)) => object.setk(key, value),
+                        (Some(key), None) => {
+                            object.get(key).map(|value| self.data.vstack.push(value))
+                        }
+                        _ => unreachable!(),
+                    };
+                    if let Err(msg) = result {
+                        self.panic(msg)?;
+                    }
                 }
             }
 
@@ -363,6 +651,223 @@ impl Vm {
         self.run_object(co)
     }
 
+    // runs a virtual method. the receiver and `argc - 1` arguments are expected
+    // on the stack and will be popped into locals by the callee.
+    fn call_virtual(
+        &mut self,
+        name: &str,
+        owner: Option<Name>,
+        cb: CodeObjectRef,
+        argc: usize,
+    ) -> VmResult {
+        let expected = {
+            let callee: &CodeObject = cb.borrow();
+            callee.argc
+        };
+        if expected != argc {
+            self.panic(format!(
+                "method `{}` expects {} arguments (including self) but got {}",
+                name, expected, argc
+            ))?;
+        }
+        self.calls.push(Call {
+            name: name.to_string(),
+            owner,
+        });
+        let result = self.run_object(cb);
+        self.calls.pop();
+        result
+    }
+
+    // calls the method `name` of the object `receiver` with `args`. returns `None`
+    // if the object does not define the method.
+    fn call_method(
+        &mut self,
+        ip: usize,
+        name: &str,
+        receiver: &Value,
+        args: Vec<Value>,
+    ) -> Result<Option<Value>, String> {
+        let object = match resolve(&self.data, receiver) {
+            Ok(object) => object,
+            Err(msg) => self.panic(msg)?,
+        };
+        match find_method(&object, &Value::from(name), None) {
+            Some((owner, ObjectMethod::Virtual(cb))) => {
+                let base = self.data.vstack.len();
+                let argc = args.len() + 1;
+                self.data.vstack.push(receiver.clone());
+                self.data.vstack.extend(args);
+                self.call_virtual(name, owner, cb, argc)?;
+                match self.data.vstack.len() {
+                    len if len == base => Ok(Some(Value::Nil)),
+                    _ => Ok(self.data.vstack.pop()),
+                }
+            }
+            Some((_, ObjectMethod::Native)) => {
+                let mut ctx = CallContext {
+                    site: self.site(ip),
+                    data: &mut self.data,
+                };
+                let result = object.borrow_mut().call(&name.to_string(), args, &mut ctx);
+                match result {
+                    Ok(value) => Ok(Some(value.unwrap_or(Value::Nil))),
+                    Err(msg) => self.panic(msg),
+                }
+            }
+            _ => Ok(None),
+        }
+    }
+
+    // dispatches a binary operator on objects. the method of the left operand is
+    // preferred; the reflected method of the right operand is used as fallback.
+    fn call_operator(
+        &mut self,
+        ip: usize,
+        (method, reflected): (&str, &str),
+        lhs: Value,
+        rhs: Value,
+    ) -> Result<Value, String> {
+        if let Some(result) = self.try_operator(ip, (method, reflected), &lhs, &rhs)? {
+            return Ok(result);
+        }
+        let tried = [(method, &lhs), (reflected, &rhs)];
+        let tried = tried.iter().filter(|(_, value)| is_ref(value)).cloned();
+        let msg = undefined_operator(&self.data, &tried.collect::<Vec<_>>());
+        self.panic(msg)
+    }
+
+    // like `call_operator` but `None` if neither operand defines the method
+    fn try_operator(
+        &mut self,
+        ip: usize,
+        (method, reflected): (&str, &str),
+        lhs: &Value,
+        rhs: &Value,
+    ) -> Result<Option<Value>, String> {
+        if is_ref(lhs) {
+            if let Some(result) = self.call_method(ip, method, lhs, vec![rhs.clone()])? {
+                return Ok(Some(result));
+            }
+        }
+        if is_ref(rhs) {
+            if let Some(result) = self.call_method(ip, reflected, rhs, vec![lhs.clone()])? {
+                return Ok(Some(result));
+            }
+        }
+        Ok(None)
+    }
+
+    // comparisons are reflected by swapping the operands, e.g. `1 < obj` calls
+    // `obj.__gt__(1)`. `__ne__` falls back to the negation of `__eq__`. objects
+    // without `__eq__` are compared by identity.
+    fn compare_objects(
+        &mut self,
+        ip: usize,
+        inx: Code,
+        lhs: Value,
+        rhs: Value,
+    ) -> Result<bool, String> {
+        let methods = match inx {
+            Code::CmpEq => ("__eq__", "__eq__"),
+            Code::CmpNe => ("__ne__", "__ne__"),
+            Code::CmpGe => ("__ge__", "__le__"),
+            Code::CmpGt => ("__gt__", "__lt__"),
+            Code::CmpLe => ("__le__", "__ge__"),
+            Code::CmpLt => ("__lt__", "__gt__"),
+            _ => unreachable!(),
+        };
+        if let Code::CmpEq | Code::CmpNe = inx {
+            if inx == Code::CmpNe {
+                if let Some(result) = self.try_operator(ip, methods, &lhs, &rhs)? {
+                    return self.comparison_result(methods.0, result);
+                }
+            }
+            let eq = match self.try_operator(ip, ("__eq__", "__eq__"), &lhs, &rhs)? {
+                Some(result) => self.comparison_result("__eq__", result)?,
+                _ => lhs.loose_eq(&rhs),
+            };
+            return Ok(eq == (inx == Code::CmpEq));
+        }
+        let result = self.call_operator(ip, methods, lhs, rhs)?;
+        self.comparison_result(methods.0, result)
+    }
+
+    // comparison methods have to return a boolean; nil counts as `false`
+    fn comparison_result(&mut self, method: &str, result: Value) -> Result<bool, String> {
+        match result {
+            Value::T(cond) => Ok(cond),
+            Value::Nil => Ok(false),
+            other => self.panic(format!(
+                "`{}` must return a boolean but returned `{}`",
+                method, other
+            )),
+        }
+    }
+
+    fn site(&self, ip: usize) -> AllocSite {
+        AllocSite::new(self.calls.last().map(|call| call.name.clone()), ip)
+    }
+
+    // called before every allocation
+    fn collect_if_needed(&mut self, ip: usize) -> VmResult {
+        if self.data.obj_pool.should_collect() {
+            self.data.collect_garbage();
+            self.run_finalizers(ip)?;
+        }
+        Ok(())
+    }
+
+    // runs the finalizers of all objects queued by the collector. the first
+    // failing finalizer stops execution; its object is released anyway.
+    fn run_finalizers(&mut self, ip: usize) -> VmResult {
+        // objects released by a finalizer are handled by the outer loop
+        if self.finalizing {
+            return Ok(());
+        }
+        self.finalizing = true;
+        let mut result = Ok(());
+        while let Some(handle) = self.data.obj_pool.next_finalizable() {
+            result = self.finalize(handle, ip);
+            self.data.obj_pool.dispose_handle(&handle);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.finalizing = false;
+        result
+    }
+
+    // user types are finalized by their virtual `__drop__` method
+    fn finalize(&mut self, handle: ObjectId, ip: usize) -> VmResult {
+        let receiver = Value::Ref(handle);
+        let object = match resolve(&self.data, &receiver) {
+            Ok(object) => object,
+            Err(msg) => self.panic(msg)?,
+        };
+        let result = match find_method(&object, &Value::from("__drop__"), None) {
+            Some((owner, ObjectMethod::Virtual(cb))) => {
+                let base = self.data.vstack.len();
+                self.data.vstack.push(receiver);
+                let result = self.call_virtual("__drop__", owner, cb, 1);
+                self.data.vstack.truncate(base);
+                result
+            }
+            _ => {
+                let mut ctx = CallContext {
+                    site: self.site(ip),
+                    data: &mut self.data,
+                };
+                let result = object.borrow_mut().finalize(&mut ctx);
+                result
+            }
+        };
+        match result {
+            Err(msg) => self.panic(format!("finalizer of object `{}` failed: {}", handle, msg)),
+            ok => ok,
+        }
+    }
+
     fn push_frame(&mut self, argc: usize) {
         self.data.stack.push(VmFrame::new(argc));
     }
@@ -381,10 +886,58 @@ impl Vm {
     }
 }
 
-fn object_mut(vm: &mut VmData) -> std::cell::RefMut<dyn ObjectProtocol> {
-    match vm.vstack.last().expect("no object ref") {
-        Value::Ref(handle) => vm.obj_pool.get_mut(&handle).unwrap().borrow_mut(),
-        _ => unimplemented!(),
+// resolves the handle on top of the stack
+fn object_ref(vm: &VmData) -> Result<ObjectRef, String> {
+    resolve(vm, vm.vstack.last().expect("no object ref"))
+}
+
+// looks up the method `name` of `object`. for user-defined objects, the type
+// defining the method is returned as well. `after` restricts the lookup to the
+// ancestors of the given type.
+fn find_method(
+    object: &ObjectRef,
+    name: &Value,
+    after: Option<&Name>,
+) -> Option<(Option<Name>, ObjectMethod)> {
+    let object = object.borrow_mut();
+    match object.as_any().downcast_ref::<Object>() {
+        Some(object) => object
+            .resolve(&name.to_string(), after)
+            .map(|(owner, cb)| (Some(owner), ObjectMethod::Virtual(cb))),
+        _ if after.is_some() => None,
+        _ => object.lookup(name).map(|method| (None, method)),
+    }
+}
+
+fn is_ref(value: &Value) -> bool {
+    matches!(value, Value::Ref(_))
+}
+
+// lists the operator methods that were looked up on the given objects
+fn undefined_operator(vm: &VmData, tried: &[(&str, &Value)]) -> String {
+    let tried = tried
+        .iter()
+        .map(|(method, value)| {
+            let ty = resolve(vm, value).ok().and_then(|object| {
+                let object = object.borrow_mut();
+                object
+                    .as_any()
+                    .downcast_ref::<Object>()
+                    .and_then(|object| object.type_name().cloned())
+            });
+            match ty {
+                Some(ty) => format!("`{}` on type `{}`", method, ty),
+                _ => format!("`{}` on object `{}`", method, value),
+            }
+        })
+        .collect::<Vec<_>>();
+    format!("operator is not defined: no method {}", tried.join(" or "))
+}
+
+fn resolve(vm: &VmData, value: &Value) -> Result<ObjectRef, String> {
+    match value {
+        Value::Ref(handle) => vm.obj_pool.resolve(handle).cloned(),
+        other => Err(format!("expected object handle but got `{}`", other)),
     }
 }
 
@@ -1,60 +1,227 @@
 use super::*;
 
 #[derive(Clone, Debug, PartialEq)]
-pub struct Array(Vec<Value>);
+pub struct Array {
+    items: Vec<Value>,
+    frozen: bool,
+}
 
 impl Array {
     pub fn new() -> Self {
-        Self(vec![])
+        Self::from(vec![])
     }
 
     pub fn inner(&self) -> &Vec<Value> {
-        &self.0
+        &self.items
     }
 
     pub fn inner_mut(&mut self) -> &mut Vec<Value> {
-        &mut self.0
+        &mut self.items
+    }
+
+    // position of `key` inside the array. negative indices count from the end.
+    // `insert` may also address the position right after the last element.
+    fn index(&self, key: &Value, allow_end: bool) -> Result<usize, String> {
+        let len = self.items.len() as i64;
+        let idx = as_int(key)?;
+        let pos = if idx < 0 { idx + len } else { idx };
+        if 0 <= pos && (pos < len || allow_end && pos == len) {
+            Ok(pos as usize)
+        } else {
+            Err(format!(
+                "index `{}` out of range for array of length {}",
+                idx, len
+            ))
+        }
+    }
+
+    // like `index` but clamps the position into `0..=len`; used for slicing
+    fn bound(&self, key: &Value) -> Result<usize, String> {
+        let len = self.items.len() as i64;
+        let idx = as_int(key)?;
+        let pos = if idx < 0 { idx + len } else { idx };
+        Ok(pos.max(0).min(len) as usize)
+    }
+
+    // the items from `start` up to `end` or the last item
+    pub fn slice(&self, start: &Value, end: Option<&Value>) -> Result<Vec<Value>, String> {
+        let start = self.bound(start)?;
+        let end = match end {
+            Some(end) => self.bound(end)?,
+            _ => self.items.len(),
+        };
+        if start < end {
+            Ok(self.items[start..end].to_vec())
+        } else {
+            Ok(vec![])
+        }
     }
 }
 
 impl From<Vec<Value>> for Array {
-    fn from(from: Vec<Value>) -> Self {
-        Self(from)
+    fn from(items: Vec<Value>) -> Self {
+        Self {
+            items,
+            frozen: false,
+        }
+    }
+}
+
+fn as_int(key: &Value) -> Result<i64, String> {
+    match key.try_cast(&Value::I64(0)) {
+        Ok(Value::I64(idx)) => Ok(idx),
+        _ => Err(format!("array index must be an integer but got `{}`", key)),
     }
 }
 
 impl ObjectProtocol for Array {
     fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
         match key.to_string().as_ref() {
-            "len" => Some(ObjectMethod::Native),
+            "len" | "push" | "pop" | "insert" | "remove" | "slice" | "concat" | "reverse"
+            | "sort" | "contains" | "index_of" | "join" | "clear" => Some(ObjectMethod::Native),
             _ => None,
         }
     }
 
-    fn call(&mut self, name: &Name) -> Result<Option<Value>, ()> {
+    fn call(
+        &mut self,
+        name: &Name,
+        args: Vec<Value>,
+        ctx: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        if let "push" | "pop" | "insert" | "remove" | "reverse" | "sort" | "clear" = name.as_ref() {
+            ensure_mutable(self.frozen, "array")?;
+        }
         match name.as_ref() {
-            "len" => Ok(Some(Value::from(self.0.len()))),
-            _ => Err(()),
+            "len" => {
+                expect_args(name, &args, 0..=0)?;
+                Ok(Some(Value::from(self.items.len())))
+            }
+            "push" => {
+                self.items.extend(args);
+                Ok(None)
+            }
+            "pop" => {
+                expect_args(name, &args, 0..=0)?;
+                match self.items.pop() {
+                    Some(value) => Ok(Some(value)),
+                    _ => Err("cannot pop from empty array".to_string()),
+                }
+            }
+            "insert" => {
+                expect_args(name, &args, 2..=2)?;
+                let idx = self.index(&args[0], true)?;
+                self.items.insert(idx, args[1].clone());
+                Ok(None)
+            }
+            "remove" => {
+                expect_args(name, &args, 1..=1)?;
+                let idx = self.index(&args[0], false)?;
+                Ok(Some(self.items.remove(idx)))
+            }
+            "slice" => {
+                expect_args(name, &args, 1..=2)?;
+                let slice = self.slice(&args[0], args.get(1))?;
+                Ok(Some(ctx.alloc(Array::from(slice))))
+            }
+            // the argument can be an array or a tuple
+            "concat" => {
+                expect_args(name, &args, 1..=1)?;
+                let other = match ctx.with_object(&args[0], |other: &Array| other.items.clone()) {
+                    Ok(other) => other.unwrap_or_else(|| self.items.clone()),
+                    _ => ctx
+                        .with_object(&args[0], |other: &Tuple| other.inner().clone())?
+                        .unwrap_or_default(),
+                };
+                let mut values = self.items.clone();
+                values.extend(other);
+                Ok(Some(ctx.alloc(Array::from(values))))
+            }
+            "reverse" => {
+                expect_args(name, &args, 0..=0)?;
+                self.items.reverse();
+                Ok(None)
+            }
+            "sort" => {
+                expect_args(name, &args, 0..=0)?;
+                self.items.sort();
+                Ok(None)
+            }
+            "contains" => {
+                expect_args(name, &args, 1..=1)?;
+                Ok(Some(Value::T(
+                    self.items.iter().any(|v| v.loose_eq(&args[0])),
+                )))
+            }
+            // evaluates to `nil` if the item is not contained
+            "index_of" => {
+                expect_args(name, &args, 1..=1)?;
+                let idx = self.items.iter().position(|v| v.loose_eq(&args[0]));
+                Ok(Some(idx.map_or(Value::Nil, Value::from)))
+            }
+            "join" => {
+                expect_args(name, &args, 0..=1)?;
+                let sep = args.first().map_or(String::new(), |sep| sep.to_string());
+                let parts = self.items.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+                Ok(Some(Value::Str(parts.join(&sep))))
+            }
+            "clear" => {
+                expect_args(name, &args, 0..=0)?;
+                self.items.clear();
+                Ok(None)
+            }
+            _ => Err(format!("method `{}` not found", name)),
         }
     }
 
     fn as_indexable(&mut self) -> Result<&mut dyn Indexable, ()> {
         Ok(self as &mut dyn Indexable)
     }
+
+    fn freeze(&mut self) -> Result<(), String> {
+        self.frozen = true;
+        Ok(())
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    fn iter(&mut self, ctx: &mut CallContext) -> Result<Value, String> {
+        Ok(ctx.alloc(Iter::from(self.items.clone())))
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Value)) {
+        self.items.iter().for_each(visit);
+    }
+
+    fn print(&self, p: &mut Printer) {
+        p.write("[");
+        p.values(&self.items);
+        p.write("]");
+    }
 }
 
 impl Indexable for Array {
     fn getk(&self, key: &Value) -> Option<&Value> {
-        let idx = usize::from(key.cast(&Value::I64(0)));
-        self.0.get(idx)
+        let idx = self.index(key, false).ok()?;
+        self.items.get(idx)
     }
 
-    fn setk(&mut self, key: &Value, val: Value) {
-        let idx = usize::from(key.cast(&Value::I64(0)));
-        self.0[idx] = val;
+    fn setk(&mut self, key: &Value, val: Value) -> Result<(), String> {
+        ensure_mutable(self.frozen, "array")?;
+        let idx = self.index(key, false)?;
+        self.items[idx] = val;
+        Ok(())
     }
 
-    fn append(&mut self, v: Value) {
-        self.0.push(v);
+    fn append(&mut self, v: Value) -> Result<(), String> {
+        ensure_mutable(self.frozen, "array")?;
+        self.items.push(v);
+        Ok(())
     }
 }
@@ -1,53 +1,192 @@
 use super::*;
 
+// dicts keep their insertion order, which makes iteration and printing
+// deterministic. overwriting a key keeps its position; removing a key shifts
+// the following entries.
 #[derive(Clone, Debug, PartialEq)]
-pub struct Dict(HashMap<Value, Value>);
+pub struct Dict {
+    entries: OrderedMap,
+    frozen: bool,
+}
 
 impl Dict {
     pub fn new() -> Self {
-        Self(HashMap::new())
+        Self {
+            entries: OrderedMap::new(),
+            frozen: false,
+        }
     }
 
-    pub fn inner(&self) -> &HashMap<Value, Value> {
-        &self.0
+    pub fn inner(&self) -> &OrderedMap {
+        &self.entries
     }
 
-    pub fn inner_mut(&mut self) -> &mut HashMap<Value, Value> {
-        &mut self.0
+    pub fn inner_mut(&mut self) -> &mut OrderedMap {
+        &mut self.entries
     }
 }
 
 impl ObjectProtocol for Dict {
     fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
         match key.to_string().as_ref() {
-            "len" => Some(ObjectMethod::Native),
+            "len" | "keys" | "values" | "items" | "contains" | "remove" | "get" | "merge"
+            | "clear" => Some(ObjectMethod::Native),
             _ => None,
         }
     }
 
-    fn call(&mut self, name: &Name) -> Result<Option<Value>, ()> {
+    fn call(
+        &mut self,
+        name: &Name,
+        args: Vec<Value>,
+        ctx: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        if let "remove" | "merge" | "clear" = name.as_ref() {
+            ensure_mutable(self.frozen, "dict")?;
+        }
         match name.as_ref() {
-            "len" => Ok(Some(Value::from(self.0.len()))),
-            _ => Err(()),
+            "len" => {
+                expect_args(name, &args, 0..=0)?;
+                Ok(Some(Value::from(self.entries.len())))
+            }
+            "keys" => {
+                expect_args(name, &args, 0..=0)?;
+                let keys = self.entries.keys().cloned().collect::<Vec<_>>();
+                Ok(Some(ctx.alloc(Array::from(keys))))
+            }
+            "values" => {
+                expect_args(name, &args, 0..=0)?;
+                let values = self.entries.values().cloned().collect::<Vec<_>>();
+                Ok(Some(ctx.alloc(Array::from(values))))
+            }
+            // every item is an array of the form `[key, value]`
+            "items" => {
+                expect_args(name, &args, 0..=0)?;
+                let pairs = self
+                    .entries
+                    .iter()
+                    .map(|(key, value)| vec![key.clone(), value.clone()])
+                    .collect::<Vec<_>>();
+                let items = pairs
+                    .into_iter()
+                    .map(|pair| ctx.alloc(Array::from(pair)))
+                    .collect::<Vec<_>>();
+                Ok(Some(ctx.alloc(Array::from(items))))
+            }
+            "contains" => {
+                expect_args(name, &args, 1..=1)?;
+                Ok(Some(Value::T(
+                    self.entries.contains_key(&dict_key(&args[0])),
+                )))
+            }
+            "remove" => {
+                expect_args(name, &args, 1..=1)?;
+                match self.entries.remove(&dict_key(&args[0])) {
+                    Some(value) => Ok(Some(value)),
+                    _ => Err(format!("key `{}` does not exist", args[0])),
+                }
+            }
+            "get" => {
+                expect_args(name, &args, 1..=2)?;
+                let default = args.get(1).cloned().unwrap_or(Value::Nil);
+                Ok(Some(
+                    self.entries
+                        .get(&dict_key(&args[0]))
+                        .cloned()
+                        .unwrap_or(default),
+                ))
+            }
+            // keys of the argument overwrite existing ones
+            "merge" => {
+                expect_args(name, &args, 1..=1)?;
+                if let Some(other) =
+                    ctx.with_object(&args[0], |other: &Dict| other.entries.clone())?
+                {
+                    self.entries.extend(other);
+                }
+                Ok(None)
+            }
+            "clear" => {
+                expect_args(name, &args, 0..=0)?;
+                self.entries.clear();
+                Ok(None)
+            }
+            _ => Err(format!("method `{}` not found", name)),
         }
     }
 
     fn as_indexable(&mut self) -> Result<&mut dyn Indexable, ()> {
         Ok(self as &mut dyn Indexable)
     }
+
+    fn freeze(&mut self) -> Result<(), String> {
+        self.frozen = true;
+        Ok(())
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    // iterating a dict yields its keys
+    fn iter(&mut self, ctx: &mut CallContext) -> Result<Value, String> {
+        Ok(ctx.alloc(Iter::from(self.entries.keys().cloned().collect::<Vec<_>>())))
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+
+    fn print(&self, p: &mut Printer) {
+        p.write("{");
+        for (i, (key, value)) in self.entries.iter().enumerate() {
+            if 0 < i {
+                p.write(", ");
+            }
+            p.value(key);
+            p.write(": ");
+            p.value(value);
+        }
+        p.write("}");
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Value)) {
+        for (key, value) in self.entries.iter() {
+            visit(key);
+            visit(value);
+        }
+    }
 }
 
+// whole numbers of every width address the same entry e.g. `I32(0)` and `I(0)`.
+// they are therefore stored as `I64` if they fit.
+fn dict_key(key: &Value) -> Value {
+    match key.as_whole().map(i64::try_from) {
+        Some(Ok(n)) => Value::I64(n),
+        _ => key.clone(),
+    }
+}
+
+// `OGet` on a missing key evaluates to nil
 impl Indexable for Dict {
     fn getk(&self, key: &Value) -> Option<&Value> {
-        self.0.get(key)
+        self.entries.get(&dict_key(key))
     }
 
-    fn setk(&mut self, key: &Value, val: Value) {
-        self.0.insert(key.clone(), val);
+    fn setk(&mut self, key: &Value, val: Value) -> Result<(), String> {
+        ensure_mutable(self.frozen, "dict")?;
+        self.entries.insert(dict_key(key), val);
+        Ok(())
     }
 
-    fn append(&mut self, val: Value) {
-        let len = self.0.len();
-        self.0.insert(Value::I64(len as i64), val);
+    // stores `val` under the first unused integer key starting at `len`
+    fn append(&mut self, val: Value) -> Result<(), String> {
+        ensure_mutable(self.frozen, "dict")?;
+        let mut idx = self.entries.len() as i64;
+        while self.entries.contains_key(&Value::I64(idx)) {
+            idx += 1;
+        }
+        self.entries.insert(Value::I64(idx), val);
+        Ok(())
     }
 }
@@ -0,0 +1,58 @@
+use super::*;
+
+use std::any::TypeId;
+
+// creates a new instance of a host type for `ONew`
+pub type ObjectFactory = Rc<dyn Fn() -> ObjectRef>;
+
+#[derive(Clone)]
+struct HostType {
+    factory: ObjectFactory,
+    // used for `OInstanceOf`
+    id: TypeId,
+}
+
+// object types implemented by the embedding application. they are instantiated
+// by `ONew` if no type unit with the same name was loaded.
+#[derive(Clone, Default)]
+pub struct HostTypes(HashMap<Name, HostType>);
+
+impl HostTypes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<T, F>(&mut self, name: &str, factory: F) -> Result<(), String>
+    where
+        T: ObjectProtocol + 'static,
+        F: Fn() -> T + 'static,
+    {
+        if self.0.contains_key(name) {
+            return Err(format!("type `{}` is already registered", name));
+        }
+        let host_type = HostType {
+            factory: Rc::new(move || Rc::new(RefCell::new(factory())) as ObjectRef),
+            id: TypeId::of::<T>(),
+        };
+        self.0.insert(name.to_string(), host_type);
+        Ok(())
+    }
+
+    pub fn create(&self, name: &str) -> Option<ObjectRef> {
+        self.0.get(name).map(|host_type| (host_type.factory)())
+    }
+
+    // true if `object` was created by the factory registered as `name`
+    pub fn is_instance_of(&self, object: &dyn ObjectProtocol, name: &str) -> bool {
+        self.0
+            .get(name)
+            .is_some_and(|host_type| std::any::Any::type_id(object.as_any()) == host_type.id)
+    }
+}
+
+// factories cannot be printed, so only the names are listed
+impl std::fmt::Debug for HostTypes {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        f.debug_set().entries(self.0.keys()).finish()
+    }
+}
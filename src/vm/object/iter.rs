@@ -0,0 +1,37 @@
+use super::*;
+
+// iterator over a snapshot of values. modifying the source object while
+// iterating does not affect the items returned.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Iter {
+    items: Vec<Value>,
+    pos: usize,
+}
+
+impl From<Vec<Value>> for Iter {
+    fn from(items: Vec<Value>) -> Self {
+        Self { items, pos: 0 }
+    }
+}
+
+impl ObjectProtocol for Iter {
+    fn next(&mut self, _: &mut CallContext) -> Result<Option<Value>, String> {
+        let item = self.items.get(self.pos).cloned();
+        if item.is_some() {
+            self.pos += 1;
+        }
+        Ok(item)
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+
+    fn print(&self, p: &mut Printer) {
+        p.write("<iterator>");
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Value)) {
+        self.items[self.pos..].iter().for_each(visit);
+    }
+}
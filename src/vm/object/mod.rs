@@ -2,69 +2,329 @@ use super::*;
 
 pub mod array;
 pub mod dict;
+pub mod host;
+pub mod iter;
+pub mod ordered;
 pub mod pool;
+pub mod print;
+pub mod set;
+pub mod tuple;
 
 pub use self::array::*;
 pub use self::dict::*;
+pub use self::host::*;
+pub use self::iter::*;
+pub use self::ordered::*;
 pub use self::pool::*;
+pub use self::print::*;
+pub use self::set::*;
+pub use self::tuple::*;
 
 pub type ObjectRef = Rc<RefCell<dyn ObjectProtocol>>;
 
+// vm state handed to native methods. the receiver is borrowed while the method
+// runs, so it must not be accessed through `data.obj_pool` again.
+pub struct CallContext<'a> {
+    pub data: &'a mut VmData,
+    // location of the calling instruction; used for allocations
+    pub site: AllocSite,
+}
+
+impl CallContext<'_> {
+    // moves `object` into the pool and returns its handle
+    pub fn alloc<T>(&mut self, object: T) -> Value
+    where
+        T: ObjectProtocol + 'static,
+    {
+        let object = Rc::new(RefCell::new(object));
+        Value::Ref(self.data.obj_pool.insert(object, self.site.clone()))
+    }
+
+    // applies `f` on the object behind `value` if it is of type `T`. the receiver
+    // is the only borrowed object while a native method runs: `None` means that
+    // `value` refers to the receiver itself.
+    pub fn with_object<T, R>(
+        &self,
+        value: &Value,
+        f: impl FnOnce(&T) -> R,
+    ) -> Result<Option<R>, String>
+    where
+        T: ObjectProtocol + 'static,
+    {
+        let handle = match value {
+            Value::Ref(handle) => handle,
+            other => return Err(format!("expected object handle but got `{}`", other)),
+        };
+        let object = self.data.obj_pool.resolve(handle)?;
+        let object = match object.try_borrow() {
+            Ok(object) => object,
+            _ => return Ok(None),
+        };
+        match object.as_any().downcast_ref::<T>() {
+            Some(object) => Ok(Some(f(object))),
+            _ => Err(format!("unexpected object `{:?}`", object)),
+        }
+    }
+}
+
+// checks the argument count of a native method
+pub fn expect_args(
+    name: &Name,
+    args: &[Value],
+    range: std::ops::RangeInclusive<usize>,
+) -> Result<(), String> {
+    if range.contains(&args.len()) {
+        Ok(())
+    } else if range.start() == range.end() {
+        Err(format!(
+            "method `{}` expects {} arguments but got {}",
+            name,
+            range.start(),
+            args.len()
+        ))
+    } else {
+        Err(format!(
+            "method `{}` expects {} to {} arguments but got {}",
+            name,
+            range.start(),
+            range.end(),
+            args.len()
+        ))
+    }
+}
+
+// fails if the object was frozen. `what` names the kind of object.
+pub fn ensure_mutable(frozen: bool, what: &str) -> Result<(), String> {
+    if frozen {
+        Err(format!("cannot modify frozen {}", what))
+    } else {
+        Ok(())
+    }
+}
+
 pub enum ObjectMethod {
     Virtual(CodeObjectRef),
     // will be implemented in `call`
     Native,
 }
 
+// allows downcasting trait objects to their concrete type
+pub trait AsAny {
+    fn as_any(&self) -> &dyn std::any::Any;
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any;
+}
+
+impl<T: ObjectProtocol + 'static> AsAny for T {
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+}
+
 pub trait ObjectProtocol
 where
-    Self: std::fmt::Debug,
+    Self: AsAny + std::fmt::Debug,
 {
     fn lookup(&self, _: &Value) -> Option<ObjectMethod> {
         None
     }
 
-    // TODO: add params
-    fn call(&mut self, _: &Name) -> Result<Option<Value>, ()> {
-        unimplemented!()
+    // invoked for methods reported as `ObjectMethod::Native` by `lookup`. the
+    // receiver itself is not part of `args`; returning `None` evaluates to nil.
+    fn call(
+        &mut self,
+        name: &Name,
+        _args: Vec<Value>,
+        _ctx: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        Err(format!("method `{}` is not implemented", name))
     }
 
     fn as_indexable(&mut self) -> Result<&mut dyn Indexable, ()> {
         Err(())
     }
+
+    // used by `OIter`; returns an iterator for the object - usually the handle
+    // of a new object implementing `next`. user-defined types provide the
+    // virtual methods `__iter__` and `__next__` instead.
+    fn iter(&mut self, _ctx: &mut CallContext) -> Result<Value, String> {
+        Err("object is not iterable".to_string())
+    }
+
+    // used by `ONext`; returns the next item or `None` if exhausted
+    fn next(&mut self, _ctx: &mut CallContext) -> Result<Option<Value>, String> {
+        Err("object is not an iterator".to_string())
+    }
+
+    // used by `OFreeze`; makes the object read-only. freezing is shallow: the
+    // values stored inside the object can still be modified.
+    fn freeze(&mut self) -> Result<(), String> {
+        Err("object cannot be frozen".to_string())
+    }
+
+    fn is_frozen(&self) -> bool {
+        false
+    }
+
+    // used for printing values. nested values should be written via
+    // `Printer::value`, which resolves handles and guards against cycles.
+    fn print(&self, p: &mut Printer) {
+        p.write(&format!("{:?}", self));
+    }
+
+    // true if the object has to be finalized before it is released. user
+    // types define the virtual method `__drop__` instead.
+    fn has_finalizer(&self) -> bool {
+        false
+    }
+
+    // runs once after the object became unreachable or was disposed. the
+    // object is released afterwards even if an error is returned.
+    fn finalize(&mut self, _ctx: &mut CallContext) -> Result<(), String> {
+        Ok(())
+    }
+
+    // create an independent copy of the object. values are copied as is, so
+    // contained handles still point into the pool of the clone.
+    fn clone_box(&self) -> ObjectRef;
+
+    // visit every value stored inside the object. used by the garbage
+    // collector to find handles that are still reachable.
+    fn trace(&self, _: &mut dyn FnMut(&Value)) {}
 }
 
 impl ObjectProtocol for Object {
     fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
-        self.assoc
-            .as_ref()
-            .unwrap()
-            .0
-            .get(&key.to_string())
-            .and_then(|cb| Some(ObjectMethod::Virtual(cb)))
+        self.resolve(&key.to_string(), None)
+            .map(|(_, cb)| ObjectMethod::Virtual(cb))
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+
+    fn as_indexable(&mut self) -> Result<&mut dyn Indexable, ()> {
+        Ok(self as &mut dyn Indexable)
+    }
+
+    fn freeze(&mut self) -> Result<(), String> {
+        self.frozen = true;
+        Ok(())
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    // the finalizer itself is run by the vm
+    fn has_finalizer(&self) -> bool {
+        self.resolve(&"__drop__".to_string(), None).is_some()
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Value)) {
+        self.fields.values().for_each(visit);
+    }
+
+    // fields are sorted by name, e.g. `Point { x: 1, y: 2 }`
+    fn print(&self, p: &mut Printer) {
+        let mut fields = self.fields.iter().collect::<Vec<_>>();
+        fields.sort_by_key(|(name, _)| *name);
+        p.write(self.type_name().map_or("object", |name| name.as_ref()));
+        p.write(" {");
+        for (i, (name, value)) in fields.into_iter().enumerate() {
+            p.write(if i == 0 { " " } else { ", " });
+            p.write(&format!("{}: ", name));
+            p.value(value);
+        }
+        p.write(" }");
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+// instance of a user-defined type. methods are resolved via the units of the
+// type chain, state is kept in named fields.
+#[derive(Clone, PartialEq)]
 pub struct Object {
-    pub assoc: Option<UnitRef>,
-    pub inner: Vec<Value>,
+    pub ty: TypeChain,
+    pub fields: HashMap<Name, Value>,
+    frozen: bool,
 }
 
 impl Object {
-    pub fn new_value_assoc(assoc: UnitRef) -> Self {
+    pub fn new_value_typed(ty: TypeChain) -> Self {
         Self {
-            assoc: Some(assoc),
-            inner: vec![],
+            ty,
+            fields: HashMap::new(),
+            frozen: false,
+        }
+    }
+
+    pub fn type_name(&self) -> Option<&Name> {
+        self.ty.first().map(|(name, _)| name)
+    }
+
+    pub fn is_instance_of(&self, name: &Name) -> bool {
+        self.ty.iter().any(|(ty, _)| ty == name)
+    }
+
+    // searches the method `name` from child to root type; the first match
+    // overrides all others. if `after` is set, the search starts at the parent
+    // of that type (used for calls on `super`). returns the defining type as well.
+    pub fn resolve(&self, name: &Name, after: Option<&Name>) -> Option<(Name, CodeObjectRef)> {
+        let start = match after {
+            Some(after) => self.ty.iter().position(|(ty, _)| ty == after)? + 1,
+            _ => 0,
+        };
+        self.ty[start..]
+            .iter()
+            .find_map(|(ty, unit)| unit.0.get(name).map(|cb| (ty.clone(), cb)))
+    }
+}
+
+// the type units are left out on purpose as they would print the whole code
+impl std::fmt::Debug for Object {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        f.debug_struct("Object")
+            .field("ty", self.type_name().unwrap_or(&Name::new()))
+            .field("fields", &self.fields)
+            .finish()
+    }
+}
+
+impl Indexable for Object {
+    fn getk(&self, key: &Value) -> Option<&Value> {
+        self.fields.get(&key.to_string())
+    }
+
+    fn get(&self, key: &Value) -> Result<Value, String> {
+        match self.getk(key) {
+            Some(value) => Ok(value.clone()),
+            _ => Err(format!("object has no field `{}`", key)),
         }
     }
+
+    fn setk(&mut self, key: &Value, val: Value) -> Result<(), String> {
+        ensure_mutable(self.frozen, "object")?;
+        self.fields.insert(key.to_string(), val);
+        Ok(())
+    }
+
+    fn append(&mut self, _: Value) -> Result<(), String> {
+        Err("cannot append to object; use a named field instead".to_string())
+    }
 }
 
 // special trait to improve performance on array/dict
 pub trait Indexable: std::fmt::Debug {
     // short for "get key"
     fn getk(&self, _: &Value) -> Option<&Value>;
+    // used by `OGet`. missing keys evaluate to `Nil` unless the type decides otherwise.
+    fn get(&self, key: &Value) -> Result<Value, String> {
+        Ok(self.getk(key).cloned().unwrap_or(Value::Nil))
+    }
     // short for "set key"
-    fn setk(&mut self, _: &Value, _: Value);
-    fn append(&mut self, _: Value);
+    fn setk(&mut self, _: &Value, _: Value) -> Result<(), String>;
+    fn append(&mut self, _: Value) -> Result<(), String>;
 }
@@ -0,0 +1,122 @@
+use super::*;
+
+// a map that keeps the insertion order of its keys. the entries are stored in
+// a vector; `index` points from every key to the position of its entry.
+#[derive(Clone, Default)]
+pub struct OrderedMap {
+    entries: Vec<(Value, Value)>,
+    index: HashMap<Value, usize>,
+}
+
+impl OrderedMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn get_key_value(&self, key: &Value) -> Option<(&Value, &Value)> {
+        let (key, value) = &self.entries[*self.index.get(key)?];
+        Some((key, value))
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool {
+        self.index.contains_key(key)
+    }
+
+    // overwriting a key keeps its position. returns the previous value.
+    pub fn insert(&mut self, key: Value, value: Value) -> Option<Value> {
+        match self.index.get(&key) {
+            Some(idx) => Some(std::mem::replace(&mut self.entries[*idx].1, value)),
+            _ => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    // the following entries move up by one, so their positions are updated
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        let idx = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(idx);
+        for (key, _) in self.entries[idx..].iter() {
+            *self.index.get_mut(key).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.index.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, value)| value)
+    }
+}
+
+// like `HashMap`, maps with the same entries are equal regardless of the order
+impl PartialEq for OrderedMap {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key).is_some_and(|other| value == other))
+    }
+}
+
+impl Extend<(Value, Value)> for OrderedMap {
+    fn extend<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = (Value, Value)>,
+    {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl std::iter::FromIterator<(Value, Value)> for OrderedMap {
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (Value, Value)>,
+    {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl IntoIterator for OrderedMap {
+    type Item = (Value, Value);
+    type IntoIter = std::vec::IntoIter<(Value, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl std::fmt::Debug for OrderedMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
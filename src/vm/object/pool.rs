@@ -1,26 +1,116 @@
 use super::*;
 
+use std::collections::{HashSet, VecDeque};
+
+// minimum amount of live objects before the pool asks for a collection
+pub const VM_GC_THRESHOLD: usize = 256;
+
+// a handle is split into two parts:
+//  - the lower half addresses a slot inside the pool (starting at 1)
+//  - the upper half is the slot generation. it is bumped whenever the slot is
+//    released, so handles to a previous occupant can be told apart.
+const SLOT_BITS: u32 = usize::BITS / 2;
+const SLOT_MASK: usize = (1 << SLOT_BITS) - 1;
+
+pub fn handle_slot(handle: ObjectId) -> usize {
+    handle & SLOT_MASK
+}
+
+pub fn handle_generation(handle: ObjectId) -> usize {
+    handle >> SLOT_BITS
+}
+
+fn make_handle(slot: usize, generation: usize) -> ObjectId {
+    (generation << SLOT_BITS) | slot
+}
+
 macro_rules! spawn {
-    ($pool:expr, $obj:expr) => {{
-        $pool.last_handle += 1;
-        $pool
-            .handles
-            .insert($pool.last_handle, Rc::new(RefCell::new($obj)));
-        $pool.last_handle
+    ($pool:expr, $obj:expr, $site:expr) => {{
+        $pool.insert(Rc::new(RefCell::new($obj)), $site)
     }};
 }
 
+// the place where an object was created: function name (if known) and the
+// offset of the allocating instruction
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AllocSite {
+    pub func: Option<Name>,
+    pub ip: usize,
+}
+
+impl AllocSite {
+    pub fn new(func: Option<Name>, ip: usize) -> Self {
+        Self { func, ip }
+    }
+}
+
+impl std::fmt::Display for AllocSite {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
+        match &self.func {
+            Some(func) => write!(f, "`{}` at instruction {}", func, self.ip),
+            _ => write!(f, "instruction {}", self.ip),
+        }
+    }
+}
+
+// statistics about the object heap; can be read by the host via `Vm::heap_stats`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeapStats {
+    // objects currently stored in the pool
+    pub live: usize,
+    // total amount of allocated objects
+    pub allocated: usize,
+    // total amount of released objects
+    pub freed: usize,
+    // number of completed collection cycles
+    pub collections: usize,
+}
+
+#[derive(Debug)]
+struct Slot {
+    generation: usize,
+    object: Option<ObjectRef>,
+    site: AllocSite,
+    // value of `ObjectPool::allocated` when the object was inserted; orders finalizers
+    serial: usize,
+    // allocation site of the previous occupant; used for error reporting
+    retired: Option<AllocSite>,
+    // set by `hint_dispose`; cleared by the next collection
+    disposed: bool,
+}
+
 #[derive(Debug)]
 pub struct ObjectPool {
-    last_handle: ObjectId,
-    handles: HashMap<ObjectId, ObjectRef>,
+    slots: Vec<Slot>,
+    // indices of released slots that can be reused
+    free: Vec<usize>,
+    live: usize,
+    // collection is requested as soon as `live` reaches this value
+    threshold: usize,
+    allocated: usize,
+    freed: usize,
+    collections: usize,
+    // handles of all live tuples by their items; see `new_tuple_handle`
+    tuples: HashMap<Vec<Value>, ObjectId>,
+    // unreachable objects waiting for their finalizer; see `collect`
+    finalizable: VecDeque<ObjectId>,
+    // amount of slots marked by `hint_dispose` since the last collection
+    disposed: usize,
 }
 
 impl ObjectPool {
     pub fn new() -> Self {
         Self {
-            last_handle: 0,
-            handles: HashMap::new(),
+            slots: vec![],
+            free: vec![],
+            live: 0,
+            threshold: VM_GC_THRESHOLD,
+            allocated: 0,
+            freed: 0,
+            collections: 0,
+            tuples: HashMap::new(),
+            finalizable: VecDeque::new(),
+            disposed: 0,
         }
     }
 
@@ -28,34 +118,363 @@ impl ObjectPool {
     //    spawn!(self, Object::new_value())
     //}
 
-    pub fn new_handle_with_assoc(&mut self, unit: UnitRef) -> ObjectId {
-        spawn!(self, Object::new_value_assoc(unit))
+    pub fn new_handle_with_type(&mut self, ty: TypeChain, site: AllocSite) -> ObjectId {
+        spawn!(self, Object::new_value_typed(ty), site)
     }
 
-    pub fn new_dict_handle(&mut self) -> ObjectId {
-        spawn!(self, Dict::new())
+    pub fn new_dict_handle(&mut self, site: AllocSite) -> ObjectId {
+        spawn!(self, Dict::new(), site)
     }
 
-    pub fn new_array_handle(&mut self) -> ObjectId {
-        spawn!(self, Array::new())
+    pub fn new_array_handle(&mut self, site: AllocSite) -> ObjectId {
+        spawn!(self, Array::new(), site)
+    }
+
+    pub fn new_set_handle(&mut self, site: AllocSite) -> ObjectId {
+        spawn!(self, Set::new(), site)
+    }
+
+    // tuples are interned: equal items always result in the same handle
+    pub fn new_tuple_handle(&mut self, items: Vec<Value>, site: AllocSite) -> ObjectId {
+        match self.tuples.get(&items) {
+            Some(handle) if self.get(handle).is_some() => return *handle,
+            _ => {}
+        }
+        let handle = spawn!(self, Tuple::from(items.clone()), site);
+        self.tuples.insert(items, handle);
+        handle
+    }
+
+    pub fn insert(&mut self, object: ObjectRef, site: AllocSite) -> ObjectId {
+        self.live += 1;
+        self.allocated += 1;
+        match self.free.pop() {
+            Some(idx) => {
+                let slot = &mut self.slots[idx];
+                slot.object = Some(object);
+                slot.site = site;
+                slot.serial = self.allocated;
+                slot.disposed = false;
+                make_handle(idx + 1, slot.generation)
+            }
+            _ => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    object: Some(object),
+                    site,
+                    serial: self.allocated,
+                    retired: None,
+                    disposed: false,
+                });
+                make_handle(self.slots.len(), 0)
+            }
+        }
+    }
+
+    fn slot(&self, id: &ObjectId) -> Option<&Slot> {
+        handle_slot(*id)
+            .checked_sub(1)
+            .and_then(|idx| self.slots.get(idx))
+    }
+
+    fn release(&mut self, idx: usize) {
+        let object = match self.slots[idx].object.take() {
+            Some(object) => object,
+            _ => return,
+        };
+        if let Ok(object) = object.try_borrow() {
+            if let Some(tuple) = object.as_any().downcast_ref::<Tuple>() {
+                self.tuples.remove(tuple.inner());
+            }
+        }
+        let slot = &mut self.slots[idx];
+        slot.generation = (slot.generation + 1) & SLOT_MASK;
+        slot.retired = Some(slot.site.clone());
+        self.free.push(idx);
+        self.live -= 1;
+        self.freed += 1;
+    }
+
+    // adds every object reachable from `pending` to `marked`. returns false
+    // if the collection has to be aborted.
+    fn mark(&mut self, mut pending: Vec<ObjectId>, marked: &mut HashSet<ObjectId>) -> bool {
+        while let Some(handle) = pending.pop() {
+            if !marked.insert(handle) {
+                continue;
+            }
+            if let Some(object) = self.get(&handle).cloned() {
+                // an object that is currently borrowed cannot be traced. its
+                // children could be wrongly released, so skip this cycle.
+                let object = match object.try_borrow() {
+                    Ok(object) => object,
+                    Err(_) => {
+                        self.threshold = 2 * self.live;
+                        return false;
+                    }
+                };
+                object.trace(&mut |value| {
+                    if let Value::Ref(handle) = value {
+                        pending.push(*handle);
+                    }
+                });
+            }
+        }
+        true
+    }
+
+    // the next object queued by `collect` whose finalizer has to run. the
+    // caller releases it afterwards via `dispose_handle`.
+    pub fn next_finalizable(&mut self) -> Option<ObjectId> {
+        self.finalizable.pop_front()
+    }
+
+    // marks the object as no longer needed. it is released by the next
+    // collection if it is unreachable by then.
+    pub fn hint_dispose(&mut self, id: &ObjectId) {
+        if self.get(id).is_none() {
+            return;
+        }
+        let slot = &mut self.slots[handle_slot(*id) - 1];
+        if !slot.disposed {
+            slot.disposed = true;
+            self.disposed += 1;
+        }
     }
 
     pub fn dispose_handle(&mut self, id: &ObjectId) {
-        self.handles.remove(id);
+        if self.get(id).is_some() {
+            self.release(handle_slot(*id) - 1);
+        }
     }
 
     pub fn get(&self, id: &ObjectId) -> Option<&ObjectRef> {
-        self.handles.get(id)
+        self.slot(id)
+            .filter(|slot| slot.generation == handle_generation(*id))
+            .and_then(|slot| slot.object.as_ref())
     }
 
     pub fn get_mut(&mut self, id: &ObjectId) -> Option<&mut ObjectRef> {
-        self.handles.get_mut(id)
+        let generation = handle_generation(*id);
+        let idx = handle_slot(*id).checked_sub(1)?;
+        self.slots
+            .get_mut(idx)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.object.as_mut())
     }
+
+    // like `get` but explains why a handle could not be resolved
+    pub fn resolve(&self, id: &ObjectId) -> Result<&ObjectRef, String> {
+        if let Some(object) = self.get(id) {
+            return Ok(object);
+        }
+        let generation = handle_generation(*id);
+        match self.slot(id) {
+            Some(slot) if generation < slot.generation || slot.object.is_none() => {
+                let site = match &slot.retired {
+                    Some(site) if generation + 1 == slot.generation => site.to_string(),
+                    _ => "an unknown site".to_string(),
+                };
+                Err(format!(
+                    "use after dispose of object `{}` (slot {}, generation {}) allocated by {}",
+                    id,
+                    handle_slot(*id),
+                    generation,
+                    site
+                ))
+            }
+            _ => Err(format!("object `{}` does not exist", id)),
+        }
+    }
+
+    // text for `value` resolving handles e.g. `[1, a, {b: 2}]`
+    pub fn display(&self, value: &Value) -> String {
+        let mut p = Printer::new(self, false);
+        p.value(value);
+        p.finish()
+    }
+
+    // like `display` but strings are quoted e.g. `[1, "a", {"b": 2}]`
+    pub fn repr(&self, value: &Value) -> String {
+        let mut p = Printer::new(self, true);
+        p.value(value);
+        p.finish()
+    }
+
+    // borrows the object behind `id` as its concrete type. used by hosts to
+    // access their own object types.
+    pub fn get_as<T>(&self, id: &ObjectId) -> Result<std::cell::Ref<'_, T>, String>
+    where
+        T: ObjectProtocol + 'static,
+    {
+        let object = self.resolve(id)?;
+        let object = object
+            .try_borrow()
+            .map_err(|_| format!("object `{}` is already borrowed", id))?;
+        if !object.as_any().is::<T>() {
+            return Err(type_mismatch::<T>(id));
+        }
+        Ok(std::cell::Ref::map(object, |object| {
+            object.as_any().downcast_ref::<T>().unwrap()
+        }))
+    }
+
+    pub fn get_as_mut<T>(&self, id: &ObjectId) -> Result<std::cell::RefMut<'_, T>, String>
+    where
+        T: ObjectProtocol + 'static,
+    {
+        let object = self.resolve(id)?;
+        let object = object
+            .try_borrow_mut()
+            .map_err(|_| format!("object `{}` is already borrowed", id))?;
+        if !object.as_any().is::<T>() {
+            return Err(type_mismatch::<T>(id));
+        }
+        Ok(std::cell::RefMut::map(object, |object| {
+            object.as_any_mut().downcast_mut::<T>().unwrap()
+        }))
+    }
+
+    // where the object behind `id` was allocated
+    pub fn site(&self, id: &ObjectId) -> Option<&AllocSite> {
+        self.get(id)?;
+        self.slot(id).map(|slot| &slot.site)
+    }
+
+    pub fn len(&self) -> usize {
+        self.live
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.live == 0
+    }
+
+    // true if the amount of live objects crossed the current threshold or at
+    // least half of them were marked by `hint_dispose`. the latter keeps a
+    // loop of disposals from collecting each time.
+    pub fn should_collect(&self) -> bool {
+        self.threshold <= self.live || (0 < self.disposed && self.live <= 2 * self.disposed)
+    }
+
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            live: self.live,
+            allocated: self.allocated,
+            freed: self.freed,
+            collections: self.collections,
+        }
+    }
+
+    // mark and sweep: every object reachable from `roots` - directly or via
+    // values stored inside other objects - survives. everything else is
+    // released. returns the amount of freed objects.
+    //
+    // unreachable objects with a finalizer are not released but queued in
+    // reverse allocation order (see `next_finalizable`). they and everything
+    // they reference stay alive until the finalizer ran.
+    pub fn collect<'a, T>(&mut self, roots: T) -> usize
+    where
+        T: IntoIterator<Item = &'a Value>,
+    {
+        self.clear_disposed();
+        let mut marked = HashSet::new();
+        let pending = roots
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::Ref(handle) => Some(*handle),
+                _ => None,
+            })
+            .chain(self.finalizable.iter().cloned())
+            .collect::<Vec<_>>();
+        if !self.mark(pending, &mut marked) {
+            return 0;
+        }
+
+        let mut finalizable = vec![];
+        for idx in 0..self.slots.len() {
+            let slot = &self.slots[idx];
+            let handle = make_handle(idx + 1, slot.generation);
+            if let Some(object) = slot.object.as_ref().filter(|_| !marked.contains(&handle)) {
+                match object.try_borrow() {
+                    Ok(object) if !object.has_finalizer() => {}
+                    Ok(_) => finalizable.push((slot.serial, handle)),
+                    // like in `mark`, borrowed objects abort the collection
+                    _ => {
+                        self.threshold = 2 * self.live;
+                        return 0;
+                    }
+                }
+            }
+        }
+        finalizable.sort_by_key(|(serial, _)| std::cmp::Reverse(*serial));
+        let finalizable = finalizable.into_iter().map(|(_, handle)| handle);
+        self.finalizable.extend(finalizable);
+        let pending = self.finalizable.iter().cloned().collect();
+        if !self.mark(pending, &mut marked) {
+            return 0;
+        }
+
+        let before = self.live;
+        for idx in 0..self.slots.len() {
+            let slot = &self.slots[idx];
+            let handle = make_handle(idx + 1, slot.generation);
+            if slot.object.is_some() && !marked.contains(&handle) {
+                self.release(idx);
+            }
+        }
+        let freed = before - self.live;
+
+        self.collections += 1;
+        self.threshold = std::cmp::max(VM_GC_THRESHOLD, 2 * self.live);
+
+        freed
+    }
+
+    // every collection, including an aborted one, consumes the hints
+    fn clear_disposed(&mut self) {
+        self.slots.iter_mut().for_each(|slot| slot.disposed = false);
+        self.disposed = 0;
+    }
+}
+
+fn type_mismatch<T>(id: &ObjectId) -> String {
+    format!(
+        "object `{}` is not of type `{}`",
+        id,
+        std::any::type_name::<T>()
+    )
 }
 
+impl Clone for Slot {
+    fn clone(&self) -> Self {
+        Self {
+            generation: self.generation,
+            object: self
+                .object
+                .as_ref()
+                .map(|object| RefCell::borrow(object).clone_box()),
+            site: self.site.clone(),
+            serial: self.serial,
+            retired: self.retired.clone(),
+            disposed: self.disposed,
+        }
+    }
+}
+
+// deep copy: every object is duplicated via `clone_box` and keeps its slot and
+// generation, so handles of the original are valid in the clone as well.
 impl Clone for ObjectPool {
     fn clone(&self) -> Self {
-        // TODO: do actual clone; figure out how to copy trait objects
-        Self::new()
+        Self {
+            slots: self.slots.clone(),
+            free: self.free.clone(),
+            live: self.live,
+            threshold: self.threshold,
+            allocated: self.allocated,
+            freed: self.freed,
+            collections: self.collections,
+            tuples: self.tuples.clone(),
+            finalizable: self.finalizable.clone(),
+            disposed: self.disposed,
+        }
     }
 }
@@ -0,0 +1,78 @@
+use super::*;
+
+// renders values into text. handles are resolved through the pool and printed
+// via `ObjectProtocol::print`. in repr mode, strings are quoted.
+pub struct Printer<'a> {
+    pool: &'a ObjectPool,
+    repr: bool,
+    // objects that are currently being printed; used to detect cycles
+    visiting: Vec<ObjectId>,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    pub fn new(pool: &'a ObjectPool, repr: bool) -> Self {
+        Self {
+            pool,
+            repr,
+            visiting: vec![],
+            out: String::new(),
+        }
+    }
+
+    pub fn is_repr(&self) -> bool {
+        self.repr
+    }
+
+    pub fn write(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    pub fn value(&mut self, value: &Value) {
+        match value {
+            Value::Ref(handle) => self.object(*handle),
+            Value::Str(s) if self.repr => self.out.push_str(&format!("{:?}", s)),
+            other => self.out.push_str(&other.to_string()),
+        }
+    }
+
+    // writes `items` separated by commas
+    pub fn values<'v, T>(&mut self, items: T)
+    where
+        T: IntoIterator<Item = &'v Value>,
+    {
+        for (i, item) in items.into_iter().enumerate() {
+            if 0 < i {
+                self.write(", ");
+            }
+            self.value(item);
+        }
+    }
+
+    fn object(&mut self, handle: ObjectId) {
+        // an object containing itself is only printed once
+        if self.visiting.contains(&handle) {
+            self.write("...");
+            return;
+        }
+        let object = match self.pool.get(&handle) {
+            Some(object) => object,
+            _ => {
+                self.write(&format!("<invalid handle {}>", handle));
+                return;
+            }
+        };
+        match object.try_borrow() {
+            Ok(object) => {
+                self.visiting.push(handle);
+                object.print(self);
+                self.visiting.pop();
+            }
+            _ => self.write("..."),
+        }
+    }
+
+    pub fn finish(self) -> String {
+        self.out
+    }
+}
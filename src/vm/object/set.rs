@@ -0,0 +1,163 @@
+use super::*;
+
+// like `Dict`, sets keep their insertion order. the items are the keys of an
+// `OrderedMap` whose values are all nil.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Set {
+    items: OrderedMap,
+    frozen: bool,
+}
+
+impl Set {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inner(&self) -> &OrderedMap {
+        &self.items
+    }
+
+    pub fn inner_mut(&mut self) -> &mut OrderedMap {
+        &mut self.items
+    }
+
+    // returns `true` if the value was not contained before
+    fn insert(&mut self, value: Value) -> bool {
+        self.items.insert(value, Value::Nil).is_none()
+    }
+
+    // the set behind `value`. if `value` is the receiver itself, it is `self`.
+    fn other(&self, value: &Value, ctx: &CallContext) -> Result<OrderedMap, String> {
+        let other = ctx.with_object(value, |other: &Set| other.items.clone())?;
+        Ok(other.unwrap_or_else(|| self.items.clone()))
+    }
+}
+
+impl From<Vec<Value>> for Set {
+    fn from(from: Vec<Value>) -> Self {
+        Self {
+            items: from.into_iter().map(|item| (item, Value::Nil)).collect(),
+            frozen: false,
+        }
+    }
+}
+
+impl ObjectProtocol for Set {
+    fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
+        match key.to_string().as_ref() {
+            "len" | "add" | "remove" | "contains" | "union" | "intersection" | "difference" => {
+                Some(ObjectMethod::Native)
+            }
+            _ => None,
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &Name,
+        args: Vec<Value>,
+        ctx: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        if let "add" | "remove" = name.as_ref() {
+            ensure_mutable(self.frozen, "set")?;
+        }
+        match name.as_ref() {
+            "len" => {
+                expect_args(name, &args, 0..=0)?;
+                Ok(Some(Value::from(self.items.len())))
+            }
+            // evaluates to `true` if the value was not contained before
+            "add" => {
+                expect_args(name, &args, 1..=1)?;
+                let value = args.into_iter().next().unwrap();
+                Ok(Some(Value::T(self.insert(value))))
+            }
+            "remove" => {
+                expect_args(name, &args, 1..=1)?;
+                if self.items.remove(&args[0]).is_some() {
+                    Ok(None)
+                } else {
+                    Err(format!("value `{}` does not exist", args[0]))
+                }
+            }
+            "contains" => {
+                expect_args(name, &args, 1..=1)?;
+                Ok(Some(Value::T(self.items.contains_key(&args[0]))))
+            }
+            // the result is a new set; the receiver stays unchanged
+            "union" | "intersection" | "difference" => {
+                expect_args(name, &args, 1..=1)?;
+                let other = self.other(&args[0], ctx)?;
+                let items = match name.as_ref() {
+                    "union" => self.items.keys().chain(other.keys()).collect::<Vec<_>>(),
+                    "intersection" => self
+                        .items
+                        .keys()
+                        .filter(|item| other.contains_key(item))
+                        .collect(),
+                    _ => self
+                        .items
+                        .keys()
+                        .filter(|item| !other.contains_key(item))
+                        .collect(),
+                };
+                let result = Set::from(items.into_iter().cloned().collect::<Vec<_>>());
+                Ok(Some(ctx.alloc(result)))
+            }
+            _ => Err(format!("method `{}` not found", name)),
+        }
+    }
+
+    fn as_indexable(&mut self) -> Result<&mut dyn Indexable, ()> {
+        Ok(self as &mut dyn Indexable)
+    }
+
+    fn freeze(&mut self) -> Result<(), String> {
+        self.frozen = true;
+        Ok(())
+    }
+
+    fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+
+    fn iter(&mut self, ctx: &mut CallContext) -> Result<Value, String> {
+        Ok(ctx.alloc(Iter::from(self.items.keys().cloned().collect::<Vec<_>>())))
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+
+    // `{}` is an empty dict, so empty sets are printed as `set()`
+    fn print(&self, p: &mut Printer) {
+        if self.items.is_empty() {
+            p.write("set()");
+        } else {
+            p.write("{");
+            p.values(self.items.keys());
+            p.write("}");
+        }
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Value)) {
+        self.items.keys().for_each(visit);
+    }
+}
+
+// `OAppend` adds a value; `OGet` evaluates to the value if it is contained
+impl Indexable for Set {
+    fn getk(&self, key: &Value) -> Option<&Value> {
+        self.items.get_key_value(key).map(|(key, _)| key)
+    }
+
+    fn setk(&mut self, _: &Value, _: Value) -> Result<(), String> {
+        Err("sets do not have keys; use `add` instead".to_string())
+    }
+
+    fn append(&mut self, val: Value) -> Result<(), String> {
+        ensure_mutable(self.frozen, "set")?;
+        self.insert(val);
+        Ok(())
+    }
+}
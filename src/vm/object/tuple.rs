@@ -0,0 +1,124 @@
+use super::*;
+
+// an immutable array. tuples are interned by the `ObjectPool`: creating a tuple
+// with equal items returns the existing handle. this makes them usable as dict
+// keys because handles are compared by identity.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tuple(Array);
+
+impl Tuple {
+    pub fn inner(&self) -> &Vec<Value> {
+        self.0.inner()
+    }
+}
+
+impl From<Vec<Value>> for Tuple {
+    fn from(from: Vec<Value>) -> Self {
+        let mut array = Array::from(from);
+        array.freeze().unwrap();
+        Self(array)
+    }
+}
+
+impl ObjectProtocol for Tuple {
+    // all methods of `Array` that do not modify it and equality
+    fn lookup(&self, key: &Value) -> Option<ObjectMethod> {
+        match key.to_string().as_ref() {
+            "len" | "slice" | "concat" | "contains" | "index_of" | "join" | "__eq__" => {
+                Some(ObjectMethod::Native)
+            }
+            _ => None,
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &Name,
+        args: Vec<Value>,
+        ctx: &mut CallContext,
+    ) -> Result<Option<Value>, String> {
+        match name.as_ref() {
+            // equal tuples share their handle. `with_object` reports the
+            // receiver itself as `None`.
+            "__eq__" => {
+                expect_args(name, &args, 1..=1)?;
+                let same = matches!(ctx.with_object(&args[0], |_: &Tuple| ()), Ok(None));
+                Ok(Some(Value::T(same)))
+            }
+            // results are tuples again
+            "slice" => {
+                expect_args(name, &args, 1..=2)?;
+                let items = self.0.slice(&args[0], args.get(1))?;
+                Ok(Some(alloc_tuple(ctx, items)))
+            }
+            // the argument can be a tuple or an array
+            "concat" => {
+                expect_args(name, &args, 1..=1)?;
+                let other = match ctx.with_object(&args[0], |other: &Tuple| other.inner().clone()) {
+                    Ok(other) => other.unwrap_or_else(|| self.inner().clone()),
+                    _ => ctx
+                        .with_object(&args[0], |other: &Array| other.inner().clone())?
+                        .unwrap_or_default(),
+                };
+                let mut items = self.inner().clone();
+                items.extend(other);
+                Ok(Some(alloc_tuple(ctx, items)))
+            }
+            _ if self.lookup(&Value::from(name.as_ref())).is_some() => self.0.call(name, args, ctx),
+            _ => Err(format!("method `{}` not found", name)),
+        }
+    }
+
+    fn as_indexable(&mut self) -> Result<&mut dyn Indexable, ()> {
+        Ok(self as &mut dyn Indexable)
+    }
+
+    fn iter(&mut self, ctx: &mut CallContext) -> Result<Value, String> {
+        self.0.iter(ctx)
+    }
+
+    fn freeze(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn is_frozen(&self) -> bool {
+        true
+    }
+
+    fn clone_box(&self) -> ObjectRef {
+        Rc::new(RefCell::new(self.clone()))
+    }
+
+    // tuples with a single item keep a trailing comma, e.g. `(1,)`
+    fn print(&self, p: &mut Printer) {
+        p.write("(");
+        p.values(self.inner());
+        if self.inner().len() == 1 {
+            p.write(",");
+        }
+        p.write(")");
+    }
+
+    fn trace(&self, visit: &mut dyn FnMut(&Value)) {
+        self.0.trace(visit)
+    }
+}
+
+// tuples are interned, so they are not allocated via `CallContext::alloc`
+fn alloc_tuple(ctx: &mut CallContext, items: Vec<Value>) -> Value {
+    Value::Ref(ctx.data.obj_pool.new_tuple_handle(items, ctx.site.clone()))
+}
+
+impl Indexable for Tuple {
+    fn getk(&self, key: &Value) -> Option<&Value> {
+        self.0.getk(key)
+    }
+
+    fn setk(&mut self, _: &Value, _: Value) -> Result<(), String> {
+        Err("cannot modify tuple".to_string())
+    }
+
+    fn append(&mut self, _: Value) -> Result<(), String> {
+        Err("cannot modify tuple".to_string())
+    }
+}
@@ -2,41 +2,227 @@ use super::*;
 
 use self::Value::*;
 
+use std::convert::TryFrom;
+
 // to support operations on primitive types, lovm wraps them in special `Value` variants.
 // this includes `String` which is also used for loading/storing variables, attributes of
 // objects, and dispatching function calls.
 
-// table for whole number values
-macro_rules! iop_table {
-    ($lhs:expr, $rhs:expr, $op:tt) => {
-        match (&$lhs, $rhs.cast(&$lhs)) {
-            (I(lhs), I(rhs)) => Value::I($op(lhs.clone(), rhs.clone())),
-            (I64(lhs), I64(rhs)) => Value::I64($op(lhs.clone(), rhs.clone())),
-            (Ref(lhs), Ref(rhs)) => Value::Ref($op(lhs.clone(), rhs).clone()),
-            _ => unimplemented!(),
+pub type OpResult = Result<Value, String>;
+
+// binary instructions that can be applied to a pair of values
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    And,
+    Or,
+    Xor,
+    Shl,
+    Shr,
+}
+
+impl BinaryOp {
+    pub fn from_code(code: &Code) -> Option<Self> {
+        match code {
+            Code::Add => Some(BinaryOp::Add),
+            Code::Sub => Some(BinaryOp::Sub),
+            Code::Mul => Some(BinaryOp::Mul),
+            Code::Div => Some(BinaryOp::Div),
+            Code::Rem => Some(BinaryOp::Rem),
+            Code::Pow => Some(BinaryOp::Pow),
+            Code::And => Some(BinaryOp::And),
+            Code::Or => Some(BinaryOp::Or),
+            Code::Xor => Some(BinaryOp::Xor),
+            Code::Shl => Some(BinaryOp::Shl),
+            Code::Shr => Some(BinaryOp::Shr),
+            _ => None,
         }
-    };
+    }
+
+    // the method an object has to define for overloading the operator
+    pub fn method(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "__add__",
+            BinaryOp::Sub => "__sub__",
+            BinaryOp::Mul => "__mul__",
+            BinaryOp::Div => "__div__",
+            BinaryOp::Rem => "__rem__",
+            BinaryOp::Pow => "__pow__",
+            BinaryOp::And => "__and__",
+            BinaryOp::Or => "__or__",
+            BinaryOp::Xor => "__xor__",
+            BinaryOp::Shl => "__shl__",
+            BinaryOp::Shr => "__shr__",
+        }
+    }
+
+    // the method used if only the right operand is an object, e.g. `1 - obj`
+    // calls `obj.__rsub__(1)`
+    pub fn reflected_method(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "__radd__",
+            BinaryOp::Sub => "__rsub__",
+            BinaryOp::Mul => "__rmul__",
+            BinaryOp::Div => "__rdiv__",
+            BinaryOp::Rem => "__rrem__",
+            BinaryOp::Pow => "__rpow__",
+            BinaryOp::And => "__rand__",
+            BinaryOp::Or => "__ror__",
+            BinaryOp::Xor => "__rxor__",
+            BinaryOp::Shl => "__rshl__",
+            BinaryOp::Shr => "__rshr__",
+        }
+    }
 }
 
-// table for numeric values
-macro_rules! nop_table {
-    ($lhs:expr, $rhs:expr, $op:tt) => {
-        match (&$lhs, $rhs.cast(&$lhs)) {
-            (F64(lhs), F64(rhs)) => Value::F64($op(lhs.clone(), rhs.clone())),
-            _ => iop_table!($lhs, $rhs, $op),
+// every integer variant fits into an `i128`. the operation is therefore done in the wider
+// type and checked against the bounds of the original type afterwards. wrapping results
+// are truncated when they are converted back (see `int_arith`).
+//
+// returns `None` if the result overflowed and neither wrapping nor saturating is wanted.
+fn integer_op(
+    lhs: i128,
+    rhs: i128,
+    (min, max, bits): (i128, i128, u32),
+    op: BinaryOp,
+    mode: ArithmeticMode,
+) -> Result<Option<i128>, String> {
+    use self::BinaryOp::*;
+
+    let (wrapped, exact) = match op {
+        Add => (lhs.wrapping_add(rhs), lhs.checked_add(rhs)),
+        Sub => (lhs.wrapping_sub(rhs), lhs.checked_sub(rhs)),
+        Mul => (lhs.wrapping_mul(rhs), lhs.checked_mul(rhs)),
+        Div | Rem if rhs == 0 => return Err("division by zero".to_string()),
+        Div => (lhs / rhs, Some(lhs / rhs)),
+        Rem => (lhs % rhs, Some(lhs % rhs)),
+        // the reciprocal of a whole number truncates towards zero
+        Pow if rhs < 0 => {
+            let result = match lhs {
+                0 => return Err("division by zero".to_string()),
+                1 => 1,
+                -1 if rhs % 2 == 0 => 1,
+                -1 => -1,
+                _ => 0,
+            };
+            (result, Some(result))
+        }
+        Pow => {
+            let ex = u32::try_from(rhs).unwrap_or(u32::MAX);
+            (lhs.wrapping_pow(ex), lhs.checked_pow(ex))
+        }
+        And => (lhs & rhs, Some(lhs & rhs)),
+        Or => (lhs | rhs, Some(lhs | rhs)),
+        Xor => (lhs ^ rhs, Some(lhs ^ rhs)),
+        // bits shifted out are always discarded; only the shift amount can be invalid
+        Shl | Shr => {
+            if (rhs < 0 || bits as i128 <= rhs) && mode != ArithmeticMode::Wrapping {
+                return Err(format!("shift amount `{}` out of range", rhs));
+            }
+            let amount = (rhs as u32) & (bits - 1);
+            return Ok(Some(if op == Shl {
+                lhs << amount
+            } else {
+                lhs >> amount
+            }));
         }
     };
+
+    match exact {
+        Some(result) if min <= result && result <= max => Ok(Some(result)),
+        _ => match mode {
+            ArithmeticMode::Wrapping => Ok(Some(wrapped)),
+            ArithmeticMode::Saturating => {
+                let negative = match exact {
+                    Some(result) => result < 0,
+                    None if op == Mul => (lhs < 0) != (rhs < 0),
+                    None => lhs < 0 && rhs % 2 == 1,
+                };
+                Ok(Some(if negative { min } else { max }))
+            }
+            ArithmeticMode::Checked | ArithmeticMode::Promote => Ok(None),
+        },
+    }
 }
 
-macro_rules! pow {
-    ($lhs:expr, $rhs:expr) => {{
-        let ex = $rhs.abs() as u32;
-        if $rhs.is_negative() {
-            1 / $lhs.pow(ex)
-        } else {
-            $lhs.pow(ex)
+// upper bound for the bit length of a `BigInt` created by `Pow`
+const MAX_POW_BITS: u64 = 1 << 16;
+
+fn big_arith(lhs: &BigInt, rhs: &BigInt, op: BinaryOp) -> Result<BigInt, String> {
+    use self::BinaryOp::*;
+
+    let shift_amount = || match rhs.to_i128().map(u32::try_from) {
+        Some(Ok(amount)) => Ok(amount as usize),
+        _ => Err(format!("shift amount `{}` out of range", rhs)),
+    };
+
+    match op {
+        Add => Ok(lhs.add(rhs)),
+        Sub => Ok(lhs.sub(rhs)),
+        Mul => Ok(lhs.mul(rhs)),
+        Div | Rem => match lhs.div_rem(rhs) {
+            Some((quot, _)) if op == Div => Ok(quot),
+            Some((_, rem)) => Ok(rem),
+            _ => Err("division by zero".to_string()),
+        },
+        // the reciprocal of a whole number truncates towards zero
+        Pow if rhs.is_negative() => match lhs.to_i128() {
+            Some(0) => Err("division by zero".to_string()),
+            Some(1) => Ok(BigInt::one()),
+            Some(-1) if rhs.is_odd() => Ok(BigInt::one().neg()),
+            Some(-1) => Ok(BigInt::one()),
+            _ => Ok(BigInt::zero()),
+        },
+        // the estimated length of the result is capped as huge powers would exhaust memory.
+        // `0`, `1` and `-1` stay that small for any exponent.
+        Pow => match rhs.to_i128().map(u32::try_from) {
+            Some(Ok(ex)) if lhs.bits().saturating_sub(1) * u64::from(ex) <= MAX_POW_BITS => {
+                Ok(lhs.pow(ex))
+            }
+            _ => Err(format!("exponent `{}` too large", rhs)),
+        },
+        And => Ok(lhs.and(rhs)),
+        Or => Ok(lhs.or(rhs)),
+        Xor => Ok(lhs.xor(rhs)),
+        Shl => Ok(lhs.shl(shift_amount()?)),
+        Shr => Ok(lhs.shr(shift_amount()?)),
+    }
+}
+
+macro_rules! int_arith {
+    ($lhs:expr, $rhs:expr, $ty:ty, $variant:ident, $op:expr, $mode:expr) => {
+        integer_op(
+            i128::from($lhs),
+            i128::from($rhs),
+            (
+                <$ty>::min_value() as i128,
+                <$ty>::max_value() as i128,
+                <$ty>::BITS,
+            ),
+            $op,
+            $mode,
+        )
+        .map(|result| result.map(|n| Value::$variant(n as $ty)))
+    };
+}
+
+macro_rules! float_arith {
+    ($lhs:expr, $rhs:expr, $variant:ident, $op:expr) => {
+        match $op {
+            BinaryOp::Add => Some(Value::$variant($lhs + $rhs)),
+            BinaryOp::Sub => Some(Value::$variant($lhs - $rhs)),
+            BinaryOp::Mul => Some(Value::$variant($lhs * $rhs)),
+            BinaryOp::Div => Some(Value::$variant($lhs / $rhs)),
+            BinaryOp::Rem => Some(Value::$variant($lhs % $rhs)),
+            BinaryOp::Pow => Some(Value::$variant(powf!(*$lhs, *$rhs))),
+            _ => None,
         }
-    }};
+    };
 }
 
 macro_rules! powf {
@@ -49,24 +235,91 @@ macro_rules! powf {
     }};
 }
 
+// converts every numeric value into the primitive `$ty` wrapped by `$variant`
+macro_rules! cast_num {
+    ($val:expr, $ty:ty, $variant:ident) => {
+        match $val {
+            I(n) => Ok($variant(*n as $ty)),
+            I16(n) => Ok($variant(*n as $ty)),
+            I32(n) => Ok($variant(*n as $ty)),
+            I64(n) => Ok($variant(*n as $ty)),
+            U8(n) => Ok($variant(*n as $ty)),
+            U16(n) => Ok($variant(*n as $ty)),
+            U32(n) => Ok($variant(*n as $ty)),
+            U64(n) => Ok($variant(*n as $ty)),
+            Big(n) => Ok($variant(n.wrapping_i128() as $ty)),
+            F32(n) => Ok($variant(*n as $ty)),
+            F64(n) => Ok($variant(*n as $ty)),
+            C(c) => Ok($variant(*c as u32 as $ty)),
+            T(t) => Ok($variant(if *t { 1 as $ty } else { 0 as $ty })),
+            Str(_) => Err(()), // panic!("no implicit casting from string"),
+            Nil | Ref(_) => Err(()),
+        }
+    };
+}
+
 impl Value {
-    pub fn from_type(idx: usize) -> Value {
-        match idx {
-            1 => Value::I(0),
-            2 => Value::I64(0),
-            3 => Value::F64(0.),
-            4 => Value::Ref(0),
-            5 => Value::T(false),
-            6 => Value::C('0'),
-            7 => Value::Str(String::new()),
-            _ => panic!("type index not defined"),
+    // the default value of a type; also used as target for casting
+    pub fn from_type(ty: ValueType) -> Value {
+        match ty {
+            ValueType::Nil => Value::Nil,
+            ValueType::I => Value::I(0),
+            ValueType::I16 => Value::I16(0),
+            ValueType::I32 => Value::I32(0),
+            ValueType::I64 => Value::I64(0),
+            ValueType::U8 => Value::U8(0),
+            ValueType::U16 => Value::U16(0),
+            ValueType::U32 => Value::U32(0),
+            ValueType::U64 => Value::U64(0),
+            ValueType::Big => Value::Big(BigInt::zero()),
+            ValueType::F32 => Value::F32(0.),
+            ValueType::F64 => Value::F64(0.),
+            ValueType::Ref => Value::Ref(0),
+            ValueType::T => Value::T(false),
+            ValueType::C => Value::C('0'),
+            ValueType::Str => Value::Str(String::new()),
         }
     }
 
+    pub fn ty(&self) -> ValueType {
+        match self {
+            Nil => ValueType::Nil,
+            I(_) => ValueType::I,
+            I16(_) => ValueType::I16,
+            I32(_) => ValueType::I32,
+            I64(_) => ValueType::I64,
+            U8(_) => ValueType::U8,
+            U16(_) => ValueType::U16,
+            U32(_) => ValueType::U32,
+            U64(_) => ValueType::U64,
+            Big(_) => ValueType::Big,
+            F32(_) => ValueType::F32,
+            F64(_) => ValueType::F64,
+            Ref(_) => ValueType::Ref,
+            T(_) => ValueType::T,
+            C(_) => ValueType::C,
+            Str(_) => ValueType::Str,
+        }
+    }
+
+    pub fn cast_to(&self, ty: ValueType) -> Result<Value, String> {
+        self.try_cast(&Value::from_type(ty))
+            .map_err(|_| format!("cannot cast `{}` to `{}`", self, ty))
+    }
+
     pub fn to_string(&self) -> String {
         match self {
+            Value::Nil => "nil".to_string(),
             Value::I(n) => format!("{}", n),
+            Value::I16(n) => format!("{}", n),
+            Value::I32(n) => format!("{}", n),
             Value::I64(n) => format!("{}", n),
+            Value::U8(n) => format!("{}", n),
+            Value::U16(n) => format!("{}", n),
+            Value::U32(n) => format!("{}", n),
+            Value::U64(n) => format!("{}", n),
+            Value::Big(n) => format!("{}", n),
+            Value::F32(n) => format!("{}", n),
             Value::F64(n) => format!("{}", n),
             Value::Ref(n) => format!("{}", n),
             Value::T(t) => format!("{}", t),
@@ -81,47 +334,50 @@ impl Value {
 
     pub fn try_cast(&self, value: &Value) -> Result<Value, ()> {
         match (self, value) {
-            (I(_), I(_)) => Ok(self.clone()),
-            (I64(n), I(_)) => Ok(Value::I(*n as i8)),
-            (F64(n), I(_)) => Ok(Value::I(*n as i8)),
-            (Ref(n), I(_)) => Ok(Value::I(*n as i8)),
-            (C(c), I(_)) => Ok(Value::I(*c as i8)),
-            (T(t), I(_)) => Ok(Value::I(if *t { 1 } else { 0 })),
-
-            (I(n), I64(_)) => Ok(Value::I64(*n as i64)),
-            (I64(_), I64(_)) => Ok(self.clone()),
-            (F64(n), I64(_)) => Ok(Value::I64(*n as i64)),
-            (Ref(n), I64(_)) => Ok(Value::I64(*n as i64)),
-            (C(c), I64(_)) => Ok(Value::I64(*c as i64)),
-            (T(t), I64(_)) => Ok(Value::I64(if *t { 1 } else { 0 })),
-
-            (I(n), F64(_)) => Ok(Value::F64(*n as f64)),
-            (I64(n), F64(_)) => Ok(Value::F64(*n as f64)),
-            (F64(_), F64(_)) => Ok(self.clone()),
-            (Ref(n), F64(_)) => Ok(Value::F64(*n as f64)),
-            (C(c), F64(_)) => Ok(Value::F64((*c as i64) as f64)),
-            (T(t), F64(_)) => Ok(Value::F64(if *t { 1. } else { 0. })),
-
-            (I(n), Ref(_)) => Ok(Value::Ref(*n as usize)),
-            (I64(n), Ref(_)) => Ok(Value::Ref(*n as usize)),
-            (F64(n), Ref(_)) => Ok(Value::Ref(*n as usize)),
+            (Nil, Nil) => Ok(Nil),
+            (_, Nil) => Err(()),
+            // nil is the only falsy value that is not a boolean
+            (Nil, T(_)) => Ok(Value::T(false)),
+
+            (_, I(_)) => cast_num!(self, i8, I),
+            (_, I16(_)) => cast_num!(self, i16, I16),
+            (_, I32(_)) => cast_num!(self, i32, I32),
+            (_, I64(_)) => cast_num!(self, i64, I64),
+            (_, U8(_)) => cast_num!(self, u8, U8),
+            (_, U16(_)) => cast_num!(self, u16, U16),
+            (_, U32(_)) => cast_num!(self, u32, U32),
+            (_, U64(_)) => cast_num!(self, u64, U64),
+            (Big(n), F32(_)) => Ok(Value::F32(n.to_f64() as f32)),
+            (_, F32(_)) => cast_num!(self, f32, F32),
+            (Big(n), F64(_)) => Ok(Value::F64(n.to_f64())),
+            (_, F64(_)) => cast_num!(self, f64, F64),
+            (Big(_), Big(_)) => Ok(self.clone()),
+            (F32(n), Big(_)) => BigInt::from_f64(*n as f64).map(Big).ok_or(()),
+            (F64(n), Big(_)) => BigInt::from_f64(*n).map(Big).ok_or(()),
+            (_, Big(_)) => {
+                let big = |n: i128| Big(BigInt::from(n));
+                cast_num!(self, i128, big)
+            }
+            // object handles are never converted from or into other values
             (Ref(_), Ref(_)) => Ok(self.clone()),
-            (C(c), Ref(_)) => Ok(Value::Ref(*c as usize)),
-            (T(t), Ref(_)) => Ok(Value::Ref(if *t { 1 } else { 0 })),
+            (_, Ref(_)) | (Ref(_), _) => Err(()),
 
-            (I(n), C(_)) => Ok(Value::C((*n as u8) as char)),
-            (I64(n), C(_)) => Ok(Value::C((*n as u8) as char)),
-            (F64(n), C(_)) => Ok(Value::C((*n as u8) as char)),
-            (Ref(n), C(_)) => Ok(Value::C((*n as u8) as char)),
             (C(_), C(_)) => Ok(self.clone()),
             (T(t), C(_)) => Ok(Value::C(if *t { 't' } else { 'f' })),
+            (Nil, C(_)) | (Str(_), C(_)) => Err(()),
+            (n, C(_)) => Ok(Value::C(
+                (usize::try_from(n.clone()).map_err(|_| ())? as u8) as char,
+            )),
 
             (Str(_), Str(_)) => Ok(self.clone()),
-            (Str(_), _) => Err(()), // panic!("no implicit casting from string"),
             (_, Str(_)) => Err(()), // panic!("no implicit casting to string"),
 
             (T(_), T(_)) => Ok(self.clone()),
-            (v, T(_)) => match usize::from(v.clone()) {
+            (Str(_), T(_)) => Err(()),
+            (Big(n), T(_)) if n.is_zero() => Ok(Value::T(false)),
+            (Big(n), T(_)) if *n == BigInt::one() => Ok(Value::T(true)),
+            (Big(_), T(_)) => Err(()),
+            (v, T(_)) => match usize::try_from(v.clone()).map_err(|_| ())? {
                 0 => Ok(Value::T(false)),
                 1 => Ok(Value::T(true)),
                 _ => Err(()), // panic!("invalid numeric value when casting to boolean"),
@@ -129,106 +385,367 @@ impl Value {
         }
     }
 
-    pub fn pow(&self, rhs: &Value) -> Value {
-        match (self, rhs.cast(&self)) {
-            (I(lhs), I(rhs)) => Value::I(pow!(lhs, rhs)),
-            (I64(lhs), I64(rhs)) => Value::I64(pow!(lhs, rhs)),
-            (F64(lhs), F64(rhs)) => Value::F64(powf!(*lhs, rhs)),
-            (Ref(lhs), Ref(rhs)) => Value::Ref(lhs.pow(rhs as u32)),
-            _ => unimplemented!(),
+    // applies `op` after casting `rhs` into the type of `self`. floating point values
+    // follow IEEE 754, whole numbers behave as described by `mode` on overflow.
+    //
+    // a whole number `rhs` is not cast but used by value, so a right operand that is
+    // wider than `self` cannot silently lose bits. only the result has to fit into
+    // the type of `self`.
+    pub fn arith(&self, op: BinaryOp, rhs: &Value, mode: ArithmeticMode) -> OpResult {
+        // arbitrary precision takes precedence over fixed size integers
+        if let (Big(_), true) = (rhs, self.is_integer()) {
+            return self.cast(rhs).arith(op, rhs, mode);
+        }
+
+        let result = match (self, rhs.as_whole()) {
+            (I(lhs), Some(rhs)) => int_arith!(*lhs, rhs, i8, I, op, mode),
+            (I16(lhs), Some(rhs)) => int_arith!(*lhs, rhs, i16, I16, op, mode),
+            (I32(lhs), Some(rhs)) => int_arith!(*lhs, rhs, i32, I32, op, mode),
+            (I64(lhs), Some(rhs)) => int_arith!(*lhs, rhs, i64, I64, op, mode),
+            (U8(lhs), Some(rhs)) => int_arith!(*lhs, rhs, u8, U8, op, mode),
+            (U16(lhs), Some(rhs)) => int_arith!(*lhs, rhs, u16, U16, op, mode),
+            (U32(lhs), Some(rhs)) => int_arith!(*lhs, rhs, u32, U32, op, mode),
+            (U64(lhs), Some(rhs)) => int_arith!(*lhs, rhs, u64, U64, op, mode),
+            _ => self.arith_casted(op, rhs, mode),
+        }?;
+
+        match result {
+            Some(value) => Ok(value),
+            None => match (mode, self.promoted()) {
+                (ArithmeticMode::Promote, Some(wider)) => wider.arith(op, rhs, mode),
+                _ => Err(format!("integer overflow in `{} {:?} {}`", self, op, rhs)),
+            },
+        }
+    }
+
+    // the remaining combinations of `arith` where `rhs` is cast into the type of `self`
+    fn arith_casted(
+        &self,
+        op: BinaryOp,
+        rhs: &Value,
+        mode: ArithmeticMode,
+    ) -> Result<Option<Value>, String> {
+        let unsupported = || format!("cannot apply `{:?}` to `{}` and `{}`", op, self, rhs);
+        let casted = rhs.try_cast(self).map_err(|_| unsupported())?;
+        match (self, &casted) {
+            (T(lhs), T(rhs)) => match op {
+                BinaryOp::And => Ok(Some(Value::T(*lhs & rhs))),
+                BinaryOp::Or => Ok(Some(Value::T(*lhs | rhs))),
+                BinaryOp::Xor => Ok(Some(Value::T(*lhs ^ rhs))),
+                _ => Err(unsupported()),
+            },
+            (F32(lhs), F32(rhs)) => float_arith!(lhs, rhs, F32, op)
+                .ok_or_else(unsupported)
+                .map(Some),
+            (F64(lhs), F64(rhs)) => float_arith!(lhs, rhs, F64, op)
+                .ok_or_else(unsupported)
+                .map(Some),
+            (I(lhs), I(rhs)) => int_arith!(*lhs, *rhs, i8, I, op, mode),
+            (I16(lhs), I16(rhs)) => int_arith!(*lhs, *rhs, i16, I16, op, mode),
+            (I32(lhs), I32(rhs)) => int_arith!(*lhs, *rhs, i32, I32, op, mode),
+            (I64(lhs), I64(rhs)) => int_arith!(*lhs, *rhs, i64, I64, op, mode),
+            (U8(lhs), U8(rhs)) => int_arith!(*lhs, *rhs, u8, U8, op, mode),
+            (U16(lhs), U16(rhs)) => int_arith!(*lhs, *rhs, u16, U16, op, mode),
+            (U32(lhs), U32(rhs)) => int_arith!(*lhs, *rhs, u32, U32, op, mode),
+            (U64(lhs), U64(rhs)) => int_arith!(*lhs, *rhs, u64, U64, op, mode),
+            (Big(lhs), Big(rhs)) => big_arith(lhs, rhs, op).map(|n| Some(Value::Big(n))),
+            _ => Err(unsupported()),
+        }
+    }
+
+    // the value of a fixed size whole number
+    pub fn as_whole(&self) -> Option<i128> {
+        match self {
+            I(n) => Some(i128::from(*n)),
+            I16(n) => Some(i128::from(*n)),
+            I32(n) => Some(i128::from(*n)),
+            I64(n) => Some(i128::from(*n)),
+            U8(n) => Some(i128::from(*n)),
+            U16(n) => Some(i128::from(*n)),
+            U32(n) => Some(i128::from(*n)),
+            U64(n) => Some(i128::from(*n)),
+            _ => None,
+        }
+    }
+
+    // fixed size whole numbers
+    pub fn is_integer(&self) -> bool {
+        matches!(
+            self,
+            I(_) | I16(_) | I32(_) | I64(_) | U8(_) | U16(_) | U32(_) | U64(_)
+        )
+    }
+
+    // the next wider variant used by `ArithmeticMode::Promote`
+    pub fn promoted(&self) -> Option<Value> {
+        match self {
+            I(_) | I16(_) | I32(_) => Some(self.cast(&Value::I64(0))),
+            U8(_) | U16(_) | U32(_) => Some(self.cast(&Value::U64(0))),
+            I64(_) | U64(_) => Some(self.cast(&Value::Big(BigInt::zero()))),
+            _ => None,
+        }
+    }
+
+    pub fn negate(&self, mode: ArithmeticMode) -> OpResult {
+        match self {
+            I(_) | I16(_) | I32(_) | I64(_) => {
+                Value::I(0).cast(self).arith(BinaryOp::Sub, self, mode)
+            }
+            Big(v) => Ok(Value::Big(v.neg())),
+            F32(v) => Ok(Value::F32(-v)),
+            F64(v) => Ok(Value::F64(-v)),
+            T(v) => Ok(Value::T(!v)),
+            C(_) => Err("cannot negate char".to_string()),
+            Nil => Err("cannot negate nil".to_string()),
+            U8(_) | U16(_) | U32(_) | U64(_) | Str(_) => {
+                Err("cannot negate unsigned number".to_string())
+            }
+            Ref(_) => Err("cannot negate object handle".to_string()),
         }
     }
 
-    pub fn add(&self, rhs: &Self) -> Self {
-        nop_table!(self, rhs, (|l, r| l + r))
+    pub fn pow(&self, rhs: &Value) -> OpResult {
+        self.arith(BinaryOp::Pow, rhs, ArithmeticMode::default())
+    }
+
+    pub fn add(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Add, rhs, ArithmeticMode::default())
     }
 
-    pub fn sub(&self, rhs: &Self) -> Self {
-        nop_table!(self, rhs, (|l, r| l - r))
+    pub fn sub(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Sub, rhs, ArithmeticMode::default())
     }
 
-    pub fn mul(&self, rhs: &Self) -> Self {
-        nop_table!(self, rhs, (|l, r| l * r))
+    pub fn mul(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Mul, rhs, ArithmeticMode::default())
     }
 
-    pub fn div(&self, rhs: &Self) -> Self {
-        nop_table!(self, rhs, (|l, r| l / r))
+    pub fn div(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Div, rhs, ArithmeticMode::default())
     }
 
-    pub fn rem(&self, rhs: &Self) -> Self {
-        nop_table!(self, rhs, (|l, r| l % r))
+    pub fn rem(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Rem, rhs, ArithmeticMode::default())
     }
 
-    pub fn neg(&self) -> Self {
-        match *self {
-            I(v) => Value::I(-v),
-            I64(v) => Value::I64(-v),
-            F64(v) => Value::F64(-v),
-            T(v) => Value::T(!v),
-            C(_) => panic!("cannot negate char"),
-            Ref(_) | Str(_) => panic!("cannot negate unsigned number"),
+    pub fn neg(&self) -> OpResult {
+        self.negate(ArithmeticMode::default())
+    }
+
+    pub fn shl(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Shl, rhs, ArithmeticMode::default())
+    }
+
+    pub fn shr(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Shr, rhs, ArithmeticMode::default())
+    }
+
+    pub fn and(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::And, rhs, ArithmeticMode::default())
+    }
+
+    pub fn or(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Or, rhs, ArithmeticMode::default())
+    }
+
+    pub fn xor(&self, rhs: &Self) -> OpResult {
+        self.arith(BinaryOp::Xor, rhs, ArithmeticMode::default())
+    }
+}
+
+// lovm knows two kinds of equality:
+//  - strict (`PartialEq`/`Eq`): both values have the same variant and the same content.
+//    floats are compared by their bit pattern which keeps the relation reflexive for NaN
+//    and consistent with `Hash`. this is what dict keys use.
+//  - loose (`loose_eq`/`loose_cmp`): numeric variants are compared by their mathematical
+//    value regardless of type e.g. `I(1)`, `U64(1)` and `F64(1.)` are equal. floats follow
+//    IEEE 754 (NaN is unordered). other variants are only comparable to their own kind.
+//    the `Cmp*` instructions use this relation.
+//
+// strictly equal values are always loosely equal - except for NaN.
+//
+// `Ord` is a total order used for sorting. values are grouped by kind
+// (nil < bool < number < char < string < handle), numbers are ordered by value with NaN last and
+// ties between different numeric variants are resolved by variant.
+
+// common representation for comparing numeric values across variants
+enum Number {
+    Int(i128),
+    Big(BigInt),
+    Float(f64),
+}
+
+impl Number {
+    fn to_big(&self) -> BigInt {
+        match self {
+            Number::Int(n) => BigInt::from(*n),
+            Number::Big(n) => n.clone(),
+            Number::Float(_) => unreachable!(),
         }
     }
+}
 
-    pub fn shl(&self, rhs: &Self) -> Self {
-        iop_table!(self, rhs, (|l, r| l << r))
+// total order over numbers; NaN is equal to itself and greater than everything else
+fn cmp_numbers(lhs: &Number, rhs: &Number) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match (lhs, rhs) {
+        (Number::Int(lhs), Number::Int(rhs)) => lhs.cmp(rhs),
+        (Number::Float(lhs), Number::Float(rhs)) => match (lhs.is_nan(), rhs.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            _ => lhs.partial_cmp(rhs).unwrap(),
+        },
+        (Number::Float(lhs), rhs) => cmp_whole_float(rhs, *lhs).reverse(),
+        (lhs, Number::Float(rhs)) => cmp_whole_float(lhs, *rhs),
+        (lhs, rhs) => lhs.to_big().cmp(&rhs.to_big()),
     }
+}
 
-    pub fn shr(&self, rhs: &Self) -> Self {
-        iop_table!(self, rhs, (|l, r| l >> r))
+// compares a whole number with a float without losing precision
+fn cmp_whole_float(whole: &Number, float: f64) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+    match BigInt::from_f64(float) {
+        Some(trunc) => whole.to_big().cmp(&trunc).then_with(|| {
+            let fract = float.fract();
+            if 0. < fract {
+                Ordering::Less
+            } else if fract < 0. {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        }),
+        // NaN or positive infinity
+        _ if float.is_nan() || 0. < float => Ordering::Less,
+        _ => Ordering::Greater,
     }
+}
 
-    pub fn and(&self, rhs: &Self) -> Self {
-        match (&self, rhs.cast(&self)) {
-            (T(lhs), T(rhs)) => Value::T(*lhs & rhs),
-            _ => iop_table!(self, rhs, (|l, r| l & r)),
+impl Value {
+    fn as_number(&self) -> Option<Number> {
+        match self {
+            I(n) => Some(Number::Int(*n as i128)),
+            I16(n) => Some(Number::Int(*n as i128)),
+            I32(n) => Some(Number::Int(*n as i128)),
+            I64(n) => Some(Number::Int(*n as i128)),
+            U8(n) => Some(Number::Int(*n as i128)),
+            U16(n) => Some(Number::Int(*n as i128)),
+            U32(n) => Some(Number::Int(*n as i128)),
+            U64(n) => Some(Number::Int(*n as i128)),
+            Big(n) => Some(Number::Big(n.clone())),
+            F32(n) => Some(Number::Float(*n as f64)),
+            F64(n) => Some(Number::Float(*n)),
+            _ => None,
         }
     }
 
-    pub fn or(&self, rhs: &Self) -> Self {
-        match (&self, rhs.cast(&self)) {
-            (T(lhs), T(rhs)) => Value::T(*lhs | rhs),
-            _ => iop_table!(self, rhs, (|l, r| l | r)),
+    // position of the values kind inside the total order
+    fn kind_rank(&self) -> u8 {
+        match self {
+            Nil => 0,
+            T(_) => 1,
+            C(_) => 3,
+            Str(_) => 4,
+            Ref(_) => 5,
+            _ => 2,
+        }
+    }
+
+    // used to order numerically equal values of different variants
+    fn variant_rank(&self) -> u8 {
+        match self {
+            Nil => 0,
+            I(_) => 1,
+            I16(_) => 2,
+            I32(_) => 3,
+            I64(_) => 4,
+            U8(_) => 5,
+            U16(_) => 6,
+            U32(_) => 7,
+            U64(_) => 8,
+            Big(_) => 9,
+            F32(_) => 10,
+            F64(_) => 11,
+            Ref(_) => 12,
+            T(_) => 13,
+            C(_) => 14,
+            Str(_) => 15,
         }
     }
 
-    pub fn xor(&self, rhs: &Self) -> Self {
-        match (&self, rhs.cast(&self)) {
-            (T(lhs), T(rhs)) => Value::T(*lhs ^ rhs),
-            _ => iop_table!(self, rhs, (|l, r| l ^ r)),
+    // `None` if the values are of different kinds or one of them is NaN
+    pub fn loose_cmp(&self, rhs: &Value) -> Option<std::cmp::Ordering> {
+        match (self, rhs) {
+            (Nil, Nil) => Some(std::cmp::Ordering::Equal),
+            (T(lhs), T(rhs)) => Some(lhs.cmp(rhs)),
+            (C(lhs), C(rhs)) => Some(lhs.cmp(rhs)),
+            (Str(lhs), Str(rhs)) => Some(lhs.cmp(rhs)),
+            // handles can only be checked for identity
+            (Ref(lhs), Ref(rhs)) if lhs == rhs => Some(std::cmp::Ordering::Equal),
+            _ => match (self.as_number(), rhs.as_number()) {
+                (Some(Number::Float(n)), _) | (_, Some(Number::Float(n))) if n.is_nan() => None,
+                (Some(lhs), Some(rhs)) => Some(cmp_numbers(&lhs, &rhs)),
+                _ => None,
+            },
         }
     }
+
+    pub fn loose_eq(&self, rhs: &Value) -> bool {
+        self.loose_cmp(rhs) == Some(std::cmp::Ordering::Equal)
+    }
 }
 
 impl std::cmp::PartialEq for Value {
     fn eq(&self, rhs: &Self) -> bool {
-        // TODO: comparing values for equality must check the argument; add comparison for loosely
-        // typed
-        //let rhs = rhs.try_cast(&self);
-        //if rhs.is_err() {
-        //    return false;
-        //}
         match (&self, &rhs) {
+            (Nil, Nil) => true,
             (I(lhs), I(rhs)) => lhs == rhs,
+            (I16(lhs), I16(rhs)) => lhs == rhs,
+            (I32(lhs), I32(rhs)) => lhs == rhs,
             (I64(lhs), I64(rhs)) => lhs == rhs,
-            (F64(lhs), F64(rhs)) => lhs == rhs,
+            (U8(lhs), U8(rhs)) => lhs == rhs,
+            (U16(lhs), U16(rhs)) => lhs == rhs,
+            (U32(lhs), U32(rhs)) => lhs == rhs,
+            (U64(lhs), U64(rhs)) => lhs == rhs,
+            (Big(lhs), Big(rhs)) => lhs == rhs,
+            (F32(lhs), F32(rhs)) => lhs.to_bits() == rhs.to_bits(),
+            (F64(lhs), F64(rhs)) => lhs.to_bits() == rhs.to_bits(),
             (Ref(lhs), Ref(rhs)) => lhs == rhs,
             (T(lhs), T(rhs)) => lhs == rhs,
+            (C(lhs), C(rhs)) => lhs == rhs,
             (Str(lhs), Str(rhs)) => lhs == rhs,
             _ => false,
         }
     }
 }
 
+impl std::cmp::Ord for Value {
+    fn cmp(&self, rhs: &Self) -> std::cmp::Ordering {
+        self.kind_rank().cmp(&rhs.kind_rank()).then_with(|| {
+            match (self, rhs) {
+                (T(lhs), T(rhs)) => lhs.cmp(rhs),
+                (C(lhs), C(rhs)) => lhs.cmp(rhs),
+                (Str(lhs), Str(rhs)) => lhs.cmp(rhs),
+                (Ref(lhs), Ref(rhs)) => lhs.cmp(rhs),
+                _ => match (self.as_number(), rhs.as_number()) {
+                    (Some(lnum), Some(rnum)) => cmp_numbers(&lnum, &rnum)
+                        .then_with(|| self.variant_rank().cmp(&rhs.variant_rank()))
+                        .then_with(|| match (self, rhs) {
+                            // distinguish signed zeros and NaN payloads like strict equality
+                            (F32(lhs), F32(rhs)) => lhs.total_cmp(rhs),
+                            (F64(lhs), F64(rhs)) => lhs.total_cmp(rhs),
+                            _ => std::cmp::Ordering::Equal,
+                        }),
+                    _ => std::cmp::Ordering::Equal,
+                },
+            }
+        })
+    }
+}
+
 impl std::cmp::PartialOrd for Value {
     fn partial_cmp(&self, rhs: &Self) -> Option<std::cmp::Ordering> {
-        match (self, rhs.cast(&self)) {
-            (I(lhs), I(rhs)) => Some(lhs.cmp(&rhs)),
-            (I64(lhs), I64(rhs)) => Some(lhs.cmp(&rhs)),
-            (F64(lhs), F64(rhs)) => lhs.partial_cmp(&rhs),
-            (Ref(lhs), Ref(rhs)) => Some(lhs.cmp(&rhs)),
-            (T(lhs), T(rhs)) => Some(lhs.cmp(&rhs)),
-            _ => unreachable!(),
-        }
+        Some(self.cmp(rhs))
     }
 }
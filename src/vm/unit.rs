@@ -1,5 +1,9 @@
 use super::*;
 
+// a type followed by all of its ancestors
+pub type TypeChain = Vec<(Name, UnitRef)>;
+
+// loaded units and registered types. the parent of a type is declared by its unit.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Units(pub Vec<UnitRef>, HashMap<Name, UnitRef>);
 
@@ -34,10 +38,50 @@ impl Units {
         Ok(())
     }
 
+    // reloading a type replaces its methods and its parent
     pub fn load_ty(&mut self, module: &Unit, name: Name) -> Result<(), String> {
+        if let Some(parent) = &module.parent {
+            if !self.1.contains_key(parent) {
+                return Err(format!("parent type `{}` is unknown", parent));
+            }
+            if name == *parent {
+                return Err(format!("type `{}` cannot derive from itself", name));
+            }
+        }
         self.0.push(UnitRef::from(module.clone()));
         let last = self.0.last().unwrap().clone();
         self.1.insert(name, last);
         Ok(())
     }
+
+    // registers a type that inherits all methods of the already loaded `parent`
+    pub fn load_ty_derived(
+        &mut self,
+        module: &Unit,
+        name: Name,
+        parent: Name,
+    ) -> Result<(), String> {
+        let mut module = module.clone();
+        module.parent = Some(parent);
+        self.load_ty(&module, name)
+    }
+
+    pub fn parent_ty(&self, name: &Name) -> Option<Name> {
+        let unit: &Unit = self.1.get(name)?.borrow();
+        unit.parent.clone()
+    }
+
+    // the type `name` and its ancestors ordered from child to root
+    pub fn type_chain(&self, name: &Name) -> Option<TypeChain> {
+        let mut chain = vec![(name.clone(), self.lookup_ty(name)?)];
+        while let Some(parent) = self.parent_ty(&chain.last().unwrap().0) {
+            // reloading types could introduce a cycle
+            if chain.iter().any(|(ty, _)| *ty == parent) {
+                break;
+            }
+            let unit = self.lookup_ty(&parent)?;
+            chain.push((parent, unit));
+        }
+        Some(chain)
+    }
 }